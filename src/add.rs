@@ -1,23 +1,53 @@
 use config::{self, Config, Style, StyleType};
 use std::io::{self, BufRead, Read, Write};
-use std::fs::{self, File, OpenOptions};
+use std::fs::File;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use clap::ArgMatches;
+use domain::{self, Kind, Matcher};
+use git::Source;
 use errors::*;
+use dry_run;
+use dir;
+use overrides;
+use css::{self, SyntaxError};
+use preprocess;
 use userstyle;
-use reqwest;
+use remote;
+use target;
+use uri;
+use READER;
+
+// URL of the browser UI, used for all styles in the userChrome.css
+pub const CHROME_URL: &str = "chrome://browser/content/browser.xul";
 
 pub fn run(matches: &ArgMatches) -> Result<()> {
     // Make sure the /chrome folder exists
     let config = Config::load()?;
-    fs::create_dir_all(config.chrome_path)?;
+    dry_run::create_dir_all(&config.chrome_path)?;
 
-    let uris = matches.values_of_lossy("STYLE").unwrap();
+    let uris = matches.values_of_lossy("STYLE").unwrap_or_default();
+    check_uris(&uris)?;
+    let style_type = matches.value_of("type").and_then(uri::parse_type);
+    let user_chrome = matches.is_present("userchrome");
+    let force = matches.is_present("force");
+    let matchers = domain::from_matches(matches)?;
 
     for uri in uris {
         println!("");
-        add_style(&uri, matches.is_present("userchrome"), None, false)?;
+        if uri == "-" {
+            add_stdin(matches.value_of("name"), &matchers, user_chrome, force)?;
+            continue;
+        }
+
+        // Store the URI in a form that does not depend on the current directory
+        let (_, uri) = uri::classify(&uri, style_type.clone())?;
+        add_style(&uri, user_chrome, None, &matchers, false, force)?;
+    }
+
+    // Add all styles in a directory without asking for their details
+    if let Some(pattern) = matches.value_of("dir") {
+        dir::add_all(pattern, &matchers, user_chrome, force)?;
     }
 
     Ok(())
@@ -28,37 +58,121 @@ pub fn add_style(
     uri: &str,
     user_chrome: bool,
     current_style: Option<Style>,
+    matchers: &[Matcher],
     config_only: bool,
+    force: bool,
 ) -> Result<()> {
     println!("Adding '{}':", uri);
 
-    // Get current config
-    let mut config = Config::load()?;
-    let config_backup = config.clone();
-
+    // Get css and settings
+    let config = Config::load()?;
     let id = config.next_style_id();
+    let style = fetch_style(&config, uri, user_chrome, current_style, matchers, id, force)?;
 
-    // Get correct file path
-    let mut file_path = PathBuf::from(&config.chrome_path);
-    if user_chrome {
-        file_path.push("userChrome.css");
-    } else {
-        file_path.push("userContent.css");
+    install_style(style, config_only)?;
+
+    println!("Added style '{}'", uri);
+
+    Ok(())
+}
+
+// Stdin can only be read once, so it is the only style of a command
+fn check_uris(uris: &[String]) -> Result<()> {
+    if uris.len() > 1 && uris.iter().any(|uri| uri == "-") {
+        Err("A style from stdin ('-') can't be added together with other styles")?;
     }
+    Ok(())
+}
 
-    // Get css and settings
-    let stdin = io::stdin();
-    let mut style = if uri.starts_with('/') {
-        local_style(uri, id, current_style, file_path.clone(), &mut stdin.lock())?
-    } else if uri.contains('.') {
-        remote_style(uri, id, current_style, file_path.clone(), &mut stdin.lock())?
+// Add a style from stdin
+// The CSS is kept in the cache since there is no source to load it from again
+fn add_stdin(
+    name: Option<&str>,
+    matchers: &[Matcher],
+    user_chrome: bool,
+    force: bool,
+) -> Result<()> {
+    println!("Adding style from stdin:");
+
+    let name = name.ok_or("Adding a style from stdin requires '--name'")?;
+
+    let mut css = String::new();
+    let mut input = READER.lock().map_err(|_| "Unable to lift reader lock")?;
+    input.read_to_string(&mut css)?;
+
+    // The CSS is saved like the source file of a local style
+    let config = Config::load()?;
+    let id = config.next_style_id();
+    let dir = config::cache_path()?.join("stdin");
+    let path = dir.join(format!("{}.css", id));
+
+    let uri = path.to_string_lossy().into_owned();
+    let matchers = matchers.to_vec();
+    let mut style = new_style(&config, id, uri, name.to_owned(), matchers, user_chrome);
+    style.css = checked_css(&style, &css, force)?;
+
+    // Only valid styles are saved
+    dry_run::create_dir_all(&dir)?;
+    target::write(&path, &css)?;
+
+    install_style(style, false)?;
+
+    println!("Added style '{}'", name);
+
+    Ok(())
+}
+
+// Create a local style without asking the user for any details
+pub fn new_style(
+    config: &Config,
+    id: i32,
+    uri: String,
+    name: String,
+    matchers: Vec<Matcher>,
+    user_chrome: bool,
+) -> Style {
+    let matchers = if user_chrome {
+        chrome_matchers()
     } else {
-        userstyle::style(uri, id, current_style, file_path.clone(), &mut stdin.lock())?
+        matchers
     };
 
-    // Add domain to CSS
-    if let Some(ref domain) = style.domain {
-        style.css = format!("@-moz-document {} {{\n{}\n}}", domain, style.css);
+    Style {
+        id,
+        uri,
+        name,
+        matchers,
+        domain: None,
+        content_matchers: Vec::new(),
+        content_domain: None,
+        tags: Vec::new(),
+        order: config.next_order(),
+        path: target_path(config, user_chrome),
+        style_type: StyleType::Local,
+        enabled: true,
+        updated: None,
+        css_override: None,
+        http: None,
+        commit: None,
+        settings: HashMap::new(),
+        css: String::new(),
+    }
+}
+
+// Save a style with its CSS to the config and target file
+pub fn install_style(mut style: Style, config_only: bool) -> Result<()> {
+    // Get current config
+    let mut config = Config::load()?;
+    let config_backup = config.clone();
+
+    style.updated = Some(config::timestamp());
+    let file_path = style.path.clone();
+
+    // Names are used to find styles, so they have to be unique
+    let name = config.unique_name(&style.name);
+    if name != style.name {
+        println!("The name '{}' can't be used, using '{}' instead", style.name, name);
+        style.name = name;
     }
 
     // Add style to config
@@ -69,31 +183,198 @@ pub fn add_style(
 
     // Return if it should not be added to the target file
     if config_only {
-        println!("Added style '{}'", uri);
         return Ok(());
     }
 
     // Save new File
-    let start = config::RUM_START.replace("{}", &id.to_string());
-    let end = config::RUM_END.replace("{}", &id.to_string());
-    let content = start + &style.css + &end;
-
-    let mut openopts = OpenOptions::new();
-    openopts.write(true).append(true).create(true);
-    let result = openopts
-        .open(&file_path)
-        .and_then(|mut f| f.write_all(content.as_bytes()));
+    let later = config.ids_after(&style);
+    let result = target::read(&file_path).and_then(|content| {
+        let content = target::insert_block(&content, style.id, &style.css, &later);
+        target::write(&file_path, &content)
+    });
 
     // Restore config if style could not be written
     if let Err(e) = result {
-        config::restore_config(&config_backup, &Error::from(e))?;
+        config::restore_config(&config_backup, &e)?;
     }
 
-    println!("Added style '{}'", uri);
-
     Ok(())
 }
 
+// Get the matchers of the browser UI
+pub fn chrome_matchers() -> Vec<Matcher> {
+    vec![
+        Matcher {
+            kind: Kind::Url,
+            value: String::from(CHROME_URL),
+        },
+    ]
+}
+
+// Get the path of the file a style is installed to
+pub fn target_path(config: &Config, user_chrome: bool) -> PathBuf {
+    let mut file_path = PathBuf::from(&config.chrome_path);
+    if user_chrome {
+        file_path.push("userChrome.css");
+    } else {
+        file_path.push("userContent.css");
+    }
+    file_path
+}
+
+// Fetch a style and generate its CSS without writing anything
+pub fn fetch_style(
+    config: &Config,
+    uri: &str,
+    user_chrome: bool,
+    current_style: Option<Style>,
+    matchers: &[Matcher],
+    id: i32,
+    force: bool,
+) -> Result<Style> {
+    // Get correct file path
+    let path = target_path(config, user_chrome);
+
+    // Keep everything that is not part of the style's source
+    let previous = current_style.clone();
+
+    // Get css and settings
+    let stdin = io::stdin();
+    let (style_type, uri) = uri::classify(uri, None)?;
+    let mut input = stdin.lock();
+    let mut style = match style_type {
+        StyleType::Local => local_style(&uri, id, current_style, path, matchers, &mut input)?,
+        StyleType::Remote => remote_style(&uri, id, current_style, path, matchers, &mut input)?,
+        StyleType::Git => git_style(&uri, id, current_style, path, matchers, &mut input)?,
+        StyleType::Userstyle => userstyle::style(&uri, id, current_style, path, &mut input)?,
+    };
+
+    // Updated styles keep their id and their position in the target file
+    match previous {
+        Some(previous) => {
+            style.id = previous.id;
+            style.name = previous.name;
+            style.matchers = previous.matchers;
+            style.domain = previous.domain;
+            style.content_matchers = previous.content_matchers;
+            style.content_domain = previous.content_domain;
+            style.tags = previous.tags;
+            style.order = previous.order;
+            style.css_override = previous.css_override;
+        }
+        None => style.order = config.next_order(),
+    }
+
+    style.css = checked_css(&style, &style.css, force)?;
+
+    Ok(style)
+}
+
+// Create the CSS that is installed from the source CSS of a style, checking it first
+// A syntax error would also break all styles after this one in the target file
+pub fn checked_css(style: &Style, source: &str, force: bool) -> Result<String> {
+    let css = preprocess::compile(&style.uri, source)?;
+
+    // Positions are relative to the compiled CSS, before the domain is added
+    let errors = css::validate(&css);
+    if !errors.is_empty() {
+        print_errors(&style.uri, css != source, &errors);
+
+        if !force {
+            Err("Invalid CSS, use '--force' to install the style anyway")?;
+        }
+    }
+
+    wrap_css(style, &css)
+}
+
+// Compile the source of a style and make sure the result is valid CSS
+pub fn compile_valid(uri: &str, source: &str) -> Result<String> {
+    let css = preprocess::compile(uri, source)?;
+
+    let errors = css::validate(&css);
+    if !errors.is_empty() {
+        print_errors(uri, css != source, &errors);
+        Err("Invalid CSS")?;
+    }
+
+    Ok(css)
+}
+
+// Replace the installed CSS of a style with its new source, if the source is valid
+pub fn reinstall(style: &Style, source: &str) -> Result<()> {
+    let css = wrap_css(style, &compile_valid(&style.uri, source)?)?;
+
+    let content = target::read(&style.path)?;
+    let content = target::replace_block(&content, style.id, &css)
+        .ok_or("Unable to find style in target file")?;
+    target::write(&style.path, &content)
+}
+
+// Print the syntax errors of a style
+// SCSS, Less and Stylus are checked after compiling them, so the positions are in the output
+fn print_errors(uri: &str, compiled: bool, errors: &[SyntaxError]) {
+    if compiled {
+        error!("Invalid CSS after compiling '{}', positions refer to the compiled CSS:", uri);
+    } else {
+        error!("Invalid CSS in '{}':", uri);
+    }
+
+    for error in errors {
+        error!("    {}", error);
+    }
+}
+
+// Add the domain and override to compiled CSS
+fn wrap_css(style: &Style, css: &str) -> Result<String> {
+    // Add domain to CSS
+    let condition = style.condition();
+    let css = with_domain(css, condition.as_ref());
+
+    // Add the user's changes after the source
+    match style.css_override {
+        Some(ref path) => overrides::append(&css, path, condition.as_ref()),
+        None => Ok(css),
+    }
+}
+
+// Change the domain of installed CSS without fetching the style again
+pub fn replace_domain(css: &str, old: Option<&String>, new: Option<&String>) -> Result<String> {
+    let (source, css_override) = overrides::split(css);
+    let source = with_domain(without_domain(source, old)?, new);
+
+    match css_override {
+        Some(css_override) => {
+            let css_override = with_domain(without_domain(css_override, old)?, new);
+            Ok([&source, config::RUM_OVERRIDE, &css_override].concat())
+        }
+        None => Ok(source),
+    }
+}
+
+// Remove the domain that has been added by `with_domain`
+fn without_domain<'a>(css: &'a str, domain: Option<&String>) -> Result<&'a str> {
+    if domain.is_none() {
+        return Ok(css);
+    }
+
+    // Older versions did not escape the condition, so it can differ from the current one
+    let prefix = "@-moz-document ";
+    let start = match css.find(" {\n") {
+        Some(index) if css.starts_with(prefix) && css.ends_with("\n}") => index + 3,
+        _ => Err("Unable to find the domain in the installed CSS, please update the style")?,
+    };
+    Ok(&css[start..css.len() - 2])
+}
+
+// Restrict CSS to a domain
+pub fn with_domain(css: &str, domain: Option<&String>) -> String {
+    match domain {
+        Some(domain) => format!("@-moz-document {} {{\n{}\n}}", domain, css),
+        None => css.to_owned(),
+    }
+}
+
 // Read any text input from the user
 // Loops untile input is valid
 fn read_text<T: BufRead>(text: &str, input: &mut T) -> String {
@@ -132,22 +413,40 @@ fn read_domain<T: BufRead>(input: &mut T) -> Option<String> {
     }
 
     // Ask for the domain name that should be selected
-    let helptext = "Please select a target domain:\nExample: 'domain(\"kernel.org\")'\n > ";
+    let helptext = "Please select a target domain:\nExample: 'kernel.org' or \
+                    'url-prefix(\"https://kernel.org/doc/\")'\n > ";
     Some(read_text(helptext, input))
 }
 
+// Get the URLs a style should apply to from the user
+// Loops until the input is valid
+fn read_matchers<T: BufRead>(input: &mut T) -> Vec<Matcher> {
+    loop {
+        let domain = match read_domain(input) {
+            Some(domain) => domain,
+            None => return Vec::new(),
+        };
+
+        match domain::parse_input(&domain) {
+            Ok(matchers) => return matchers,
+            Err(e) => error!("Invalid domain: {}", e),
+        }
+    }
+}
+
 // Load a local style
 fn local_style<T: BufRead>(
     path: &str,
     id: i32,
     style: Option<Style>,
     file_path: PathBuf,
+    matchers: &[Matcher],
     input: &mut T,
 ) -> Result<Style> {
     let mut css = String::new();
     File::open(path)?.read_to_string(&mut css)?;
 
-    generic_style(path, id, css, style, file_path, input)
+    generic_style(path, id, css, style, file_path, matchers, input)
 }
 
 // Load a remote style
@@ -156,12 +455,34 @@ fn remote_style<T: BufRead>(
     id: i32,
     style: Option<Style>,
     file_path: PathBuf,
+    matchers: &[Matcher],
     input: &mut T,
 ) -> Result<Style> {
-    let mut css = String::new();
-    reqwest::get(url)?.read_to_string(&mut css)?;
+    let (css, http) = remote::download(url)?;
 
-    generic_style(url, id, css, style, file_path, input)
+    // Remember where the style came from for future updates
+    let mut style = generic_style(url, id, css, style, file_path, matchers, input)?;
+    style.style_type = StyleType::Remote;
+    style.http = Some(http);
+    Ok(style)
+}
+
+// Load a style from a git repository
+fn git_style<T: BufRead>(
+    uri: &str,
+    id: i32,
+    style: Option<Style>,
+    file_path: PathBuf,
+    matchers: &[Matcher],
+    input: &mut T,
+) -> Result<Style> {
+    let (css, commit) = Source::parse(uri)?.fetch()?;
+
+    // Remember which version is installed
+    let mut style = generic_style(uri, id, css, style, file_path, matchers, input)?;
+    style.style_type = StyleType::Git;
+    style.commit = Some(commit);
+    Ok(style)
 }
 
 // Generic method that creates style from CSS only
@@ -171,6 +492,7 @@ fn generic_style<T: BufRead>(
     css: String,
     style: Option<Style>,
     path: PathBuf,
+    matchers: &[Matcher],
     input: &mut T,
 ) -> Result<Style> {
     // Update existing style
@@ -179,19 +501,30 @@ fn generic_style<T: BufRead>(
         return Ok(style);
     }
 
-    // Add new style
+    // Add new style, only asking for the domain if none has been passed
     let name = read_name(input);
-    let domain = if path.ends_with("userChrome.css") {
-        Some(String::from("url(chrome://browser/content/browser.xul)"))
+    let matchers = if path.ends_with("userChrome.css") {
+        chrome_matchers()
+    } else if !matchers.is_empty() {
+        matchers.to_vec()
     } else {
-        read_domain(input)
+        read_matchers(input)
     };
 
     Ok(Style {
         id,
         name,
-        domain,
+        matchers,
+        domain: None,
+        content_matchers: Vec::new(),
+        content_domain: None,
+        tags: Vec::new(),
+        order: 0,
         path,
+        updated: None,
+        css_override: None,
+        http: None,
+        commit: None,
         enabled: true,
         uri: uri.to_owned(),
         style_type: StyleType::Local,
@@ -233,3 +566,141 @@ fn read_text__with_invalid_bytes__return_next_valid_input() {
 
     assert_eq!(result, "b");
 }
+
+#[test]
+#[allow(non_snake_case)]
+fn checked_css__with_domain__wraps_css_in_document_rule() {
+    let mut style = config::dummy_style();
+    style.domain = Some(String::from("domain(\"kernel.org\")"));
+
+    let result = checked_css(&style, "a {}", false).unwrap();
+
+    assert_eq!(result, "@-moz-document domain(\"kernel.org\") {\na {}\n}");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn new_style__with_user_chrome__uses_chrome_domain_and_target() {
+    let config = config::dummy_config(Vec::new());
+    let matchers = domain::parse_input("kernel.org").unwrap();
+
+    let style = new_style(&config, 3, String::new(), String::from("UI"), matchers, true);
+
+    assert_eq!(style.condition(), Some(format!("url(\"{}\")", CHROME_URL)));
+    assert!(style.path.ends_with("userChrome.css"));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn compile_valid__with_scss_comment_containing_quote__returns_css() {
+    let source = "// Don't break the page\na { b { color: red; } }\n";
+
+    let result = compile_valid("style.scss", source).unwrap();
+
+    assert!(result.contains("a b"));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn compile_valid__with_unclosed_brace__returns_error() {
+    let result = compile_valid("style.css", "a { color: red;");
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn checked_css__with_unclosed_brace__returns_error() {
+    let style = config::dummy_style();
+
+    let result = checked_css(&style, "a { color: red;", false);
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn checked_css__with_unclosed_brace_and_force__returns_css() {
+    let style = config::dummy_style();
+
+    let result = checked_css(&style, "a { color: red;", true).unwrap();
+
+    assert_eq!(result, "a { color: red;");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn checked_css__with_matchers__wraps_css_in_escaped_document_rule() {
+    let mut style = config::dummy_style();
+    style.matchers = vec![Matcher::new(Kind::Regexp, "https://a\\.org/.*").unwrap()];
+
+    let result = checked_css(&style, "a {}", false).unwrap();
+
+    assert_eq!(result, "@-moz-document regexp(\"https://a\\\\.org/.*\") {\na {}\n}");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn replace_domain__with_override__replaces_both_domains() {
+    let css = "@-moz-document url(chrome://a) {\na {}\n}\n/* RUM OVERRIDE */\n\
+               @-moz-document url(chrome://a) {\nb {}\n}";
+    let (old, new) = (String::from("url(\"chrome://a\")"), String::from("domain(\"b.org\")"));
+
+    let result = replace_domain(css, Some(&old), Some(&new)).unwrap();
+
+    assert_eq!(
+        result,
+        "@-moz-document domain(\"b.org\") {\na {}\n}\n/* RUM OVERRIDE */\n\
+         @-moz-document domain(\"b.org\") {\nb {}\n}"
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn replace_domain__without_old_domain__keeps_document_rule_of_source() {
+    let css = "@-moz-document domain(\"a.org\") {\na {}\n}";
+    let new = String::from("domain(\"b.org\")");
+
+    let result = replace_domain(css, None, Some(&new)).unwrap();
+
+    assert_eq!(result, format!("@-moz-document domain(\"b.org\") {{\n{}\n}}", css));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn replace_domain__with_repaired_block__returns_error() {
+    let css = "/* RUM REPAIRED 1:3: Unclosed '{' */\n@-moz-document domain(\"a.org\") {\na {}\n}";
+    let old = String::from("domain(\"a.org\")");
+
+    let result = replace_domain(css, Some(&old), None);
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn read_matchers__with_empty_regexp__asks_again() {
+    let mut cursor = io::Cursor::new(b"y\nregexp(\"\")\ny\nkernel.org");
+
+    let result = read_matchers(&mut cursor);
+
+    assert_eq!(result, vec![Matcher::new(Kind::Domain, "kernel.org").unwrap()]);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn check_uris__with_stdin_and_other_style__returns_error() {
+    let uris = vec![String::from("-"), String::from("/themes/dark.css")];
+
+    let result = check_uris(&uris);
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn check_uris__with_only_stdin__returns_ok() {
+    let result = check_uris(&[String::from("-")]);
+
+    assert!(result.is_ok());
+}
@@ -1,15 +1,24 @@
 use std::io::{self, BufRead, BufReader, Read, Write};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
 use std::fs::File;
 use errors::*;
 use std::env;
+use dry_run;
+use domain::{self, Matcher};
 use READER;
+use target;
 use toml;
 
 pub const RUM_START: &str = "\n/* RUM START {} */\n";
 pub const RUM_END: &str = "\n/* RUM END {} */\n";
+pub const RUM_OVERRIDE: &str = "\n/* RUM OVERRIDE */\n";
+pub const RUM_REPAIRED: &str = "/* RUM REPAIRED {} */\n";
+pub const RUM_QUARANTINED: &str = "/* RUM QUARANTINED {} */";
 const CONFIG_PATH: &str = ".config/rum.toml";
+const DATA_PATH: &str = ".config/rum";
+const CACHE_PATH: &str = ".cache/rum";
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
@@ -21,8 +30,14 @@ impl Config {
     // Get the next free style id
     pub fn next_style_id(&self) -> i32 {
         // Get all currently used ids
-        let mut ids: Vec<i32> = self.styles.iter().map(|s| s.id).collect();
+        // Older versions allowed numbers as names, these can't be used as ids without ambiguity
+        let mut ids: Vec<i32> = self.styles
+            .iter()
+            .map(|s| s.id)
+            .chain(self.styles.iter().filter_map(|s| s.name.parse().ok()))
+            .collect();
         ids.sort_by(|a, b| a.cmp(b));
+        ids.dedup();
 
         // Get the next free id and return it
         let mut id = 0;
@@ -46,11 +61,22 @@ impl Config {
 
         // Load the content of the file
         let path = config_path()?;
-        let mut content = String::new();
-        File::open(path)?.read_to_string(&mut content)?;
+        let content = match dry_run::read(&path) {
+            Some(content) => content,
+            None => {
+                let mut content = String::new();
+                File::open(path)?.read_to_string(&mut content)?;
+                content
+            }
+        };
 
         // Parse the file content
-        Ok(toml::from_str::<Config>(&content)?)
+        let mut config = toml::from_str::<Config>(&content)?;
+
+        // Fix entries written by older versions, these are saved with the next write
+        config.migrate();
+
+        Ok(config)
     }
 
     // Moch the load method for testing
@@ -68,6 +94,9 @@ impl Config {
 
         // Write the string to the file
         let config_path = config_path()?;
+        if dry_run::enabled() {
+            return dry_run::write(&config_path, &output);
+        }
         File::create(config_path)?.write_all(output.as_bytes())?;
 
         Ok(())
@@ -88,44 +117,166 @@ impl Config {
 
     // Remove a style and return the removed style
     // This takes the style's id or name as str
-    pub fn remove_style(&mut self, id_or_name: &str) -> Option<Style> {
-        let id = i32::from_str_radix(id_or_name, 10).unwrap_or(-1);
-
-        // Get the index of the style
-        let mut index = None;
-        for (i, style) in self.styles.iter().enumerate() {
-            if style.id == id || style.name == id_or_name {
-                index = Some(i);
-                break;
+    pub fn remove_style(&mut self, id_or_name: &str) -> Result<Style> {
+        let id = self.style_id_from_str(id_or_name)?;
+        let index = self.styles
+            .iter()
+            .position(|s| s.id == id)
+            .ok_or("Invalid style id or name")?;
+        Ok(self.styles.swap_remove(index))
+    }
+
+    // Get the position for a style that is installed after all other styles
+    pub fn next_order(&self) -> u32 {
+        self.styles.iter().map(|s| s.order + 1).max().unwrap_or(1)
+    }
+
+    // Get all styles in a target file in the order they are installed in
+    pub fn ordered_styles(&self, path: &Path) -> Vec<&Style> {
+        let mut styles: Vec<&Style> = self.styles.iter().filter(|s| s.path == path).collect();
+        styles.sort_by_key(|s| (s.order, s.id));
+        styles
+    }
+
+    // Get the ids of all styles which are installed after a style in the same target file
+    pub fn ids_after(&self, style: &Style) -> Vec<i32> {
+        self.styles
+            .iter()
+            .filter(|s| s.path == style.path && (s.order, s.id) > (style.order, style.id))
+            .map(|s| s.id)
+            .collect()
+    }
+
+    // Get the id from a string that's either the id, the name or the start of the name
+    // A tag like `@dark` can be used too, if only one style has this tag
+    // Fails if the string matches more than one style
+    pub fn style_id_from_str(&self, name_or_id: &str) -> Result<i32> {
+        if name_or_id.starts_with('@') {
+            let ids = self.tagged_ids(name_or_id)?;
+            if ids.len() > 1 {
+                Err(format!("'{}' contains multiple styles, use the id to select one", name_or_id))?;
             }
+            return Ok(ids[0]);
         }
 
-        // Remove and return the style
-        if let Some(index) = index {
-            Some(self.styles.swap_remove(index))
-        } else {
-            None
+        let id = i32::from_str_radix(name_or_id, 10)
+            .ok()
+            .filter(|&id| self.contains_style(id));
+
+        // Names can consist of digits too, so both have to be checked
+        let mut matches: Vec<&Style> = self.styles
+            .iter()
+            .filter(|s| Some(s.id) == id || s.name == name_or_id)
+            .collect();
+
+        // Only fall back to the start of the name if nothing matches exactly
+        if matches.is_empty() && !name_or_id.is_empty() {
+            let prefix = name_or_id.to_lowercase();
+            matches = self.styles
+                .iter()
+                .filter(|s| s.name.to_lowercase().starts_with(&prefix))
+                .collect();
+        }
+
+        match matches.len() {
+            0 => Err("Invalid style id or name")?,
+            1 => Ok(matches[0].id),
+            _ => {
+                let candidates: Vec<String> = matches
+                    .iter()
+                    .map(|s| format!("    ({}) {}", s.id, s.name))
+                    .collect();
+                Err(format!(
+                    "'{}' matches multiple styles, use the id to select one:\n{}",
+                    name_or_id,
+                    candidates.join("\n")
+                ))?
+            }
         }
     }
 
-    // Get the id from a string that's either the id or the name
-    pub fn style_id_from_str(&self, name_or_id: &str) -> Option<i32> {
-        if let Ok(id) = i32::from_str_radix(name_or_id, 10) {
-            // If the str is a valid int, return the style with that id
-            if self.contains_style(id) {
-                Some(id)
+    // Replace every tag like `@dark` with the ids of all styles with this tag
+    pub fn expand_tags(&self, styles: Vec<String>) -> Result<Vec<String>> {
+        let mut expanded = Vec::new();
+        for style in styles {
+            if style.starts_with('@') {
+                let ids = self.tagged_ids(&style)?;
+                expanded.extend(ids.iter().map(|id| id.to_string()));
             } else {
-                None
+                expanded.push(style);
             }
-        } else {
-            // If it's not an int, return the style with that name
-            for style in &self.styles {
-                if style.name == name_or_id {
-                    return Some(style.id);
-                }
-            }
-            None
         }
+        Ok(expanded)
+    }
+
+    // Get the ids of all styles with a tag like `@dark`
+    fn tagged_ids(&self, tag: &str) -> Result<Vec<i32>> {
+        let tag = tag.replacen("@", "", 1);
+        let ids: Vec<i32> = self.styles
+            .iter()
+            .filter(|s| s.tags.contains(&tag))
+            .map(|s| s.id)
+            .collect();
+
+        if ids.is_empty() {
+            Err(format!("No style has the tag '{}'", tag))?;
+        }
+
+        Ok(ids)
+    }
+
+    // Get a style by its id or name
+    pub fn find_style(&self, id_or_name: &str) -> Result<&Style> {
+        let id = self.style_id_from_str(id_or_name)?;
+        self.styles
+            .iter()
+            .find(|s| s.id == id)
+            .ok_or_else(|| "Invalid style id or name".into())
+    }
+
+    // Make sure a name can be used for a style without making lookups ambiguous
+    // The style with the id `id` is allowed to keep its name
+    pub fn check_name(&self, name: &str, id: Option<i32>) -> Result<()> {
+        if name.is_empty() {
+            Err("The name of a style can't be empty")?;
+        }
+
+        if is_number(name) {
+            Err("The name of a style can't be a number, it would be used as an id")?;
+        }
+
+        if name.starts_with('@') {
+            Err("The name of a style can't start with '@', it would be used as a tag")?;
+        }
+
+        if self.name_taken(name, id) {
+            Err(format!("The name '{}' is already in use", name))?;
+        }
+
+        Ok(())
+    }
+
+    // Get a name that is not used by any other style
+    // Duplicates get a number appended, like `Dark (2)`
+    // A leading `@` is removed, since the name would be read as a tag
+    pub fn unique_name(&self, name: &str) -> String {
+        let name = name.trim_start_matches('@');
+        let mut unique = name.to_owned();
+        let mut number = 2;
+        while self.name_taken(&unique, None) || is_number(&unique) {
+            unique = format!("{} ({})", name, number);
+            number += 1;
+        }
+        unique
+    }
+
+    // Check if a name is the name or id of a style other than `id`
+    // Ids are used to find styles too, so a name can not be the id of another style
+    fn name_taken(&self, name: &str, id: Option<i32>) -> bool {
+        self.styles
+            .iter()
+            .filter(|s| Some(s.id) != id)
+            .any(|s| s.name == name || s.id.to_string() == name)
     }
 
     // Change the status of a style
@@ -142,6 +293,60 @@ impl Config {
         Err("Style with this id does not exist")?
     }
 
+    // Update styles from older versions of the config
+    fn migrate(&mut self) {
+        // URL styles used to be saved as local styles
+        for style in &mut self.styles {
+            let url = style.uri.starts_with("http://") || style.uri.starts_with("https://");
+            if style.style_type == StyleType::Local && url {
+                style.style_type = StyleType::Remote;
+            }
+        }
+
+        // Styles used to be installed in the order they were added or updated in
+        // This order is taken from the target files, disabled styles are put last
+        let mut orders: Vec<u32> = self.styles.iter().map(|s| s.order).collect();
+        orders.sort();
+        orders.dedup();
+        if orders.len() != self.styles.len() {
+            let mut blocks: HashMap<PathBuf, Vec<i32>> = HashMap::new();
+            for style in &self.styles {
+                if !blocks.contains_key(&style.path) {
+                    let content = target::read(&style.path).unwrap_or_default();
+                    blocks.insert(style.path.clone(), target::block_ids(&content));
+                }
+            }
+
+            self.styles.sort_by_key(|s| {
+                let position = blocks[&s.path].iter().position(|&id| id == s.id);
+                (position.unwrap_or(usize::MAX), s.order, s.id)
+            });
+            for (i, style) in self.styles.iter_mut().enumerate() {
+                style.order = i as u32 + 1;
+            }
+        }
+
+        // Names used to be allowed more than once or as tags, which makes lookups by name fail
+        for i in 0..self.styles.len() {
+            let name = self.styles[i].name.clone();
+            if name.starts_with('@') || self.styles[..i].iter().any(|s| s.name == name) {
+                self.styles[i].name = self.unique_name(&name);
+            }
+        }
+
+        // Domains used to be saved as the raw `@-moz-document` condition
+        for style in &mut self.styles {
+            let matchers = match style.domain {
+                Some(ref condition) => domain::parse_condition(condition).and_then(|m| m.ok()),
+                None => None,
+            };
+            if let Some(matchers) = matchers {
+                style.matchers = matchers;
+                style.domain = None;
+            }
+        }
+    }
+
     // Create a new style
     fn new(chrome_path: String) -> Config {
         Config {
@@ -172,21 +377,62 @@ pub struct Style {
     #[serde(default = "default_true")] pub enabled: bool,
     pub style_type: StyleType,
     pub domain: Option<String>,
+    // Conditions of the userContent.css, kept while the style is in the userChrome.css
+    pub content_domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")] pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_zero")] pub order: u32,
+    pub updated: Option<u64>,
+    pub css_override: Option<PathBuf>,
+    pub http: Option<HttpMetadata>,
+    pub commit: Option<String>,
+    // Arrays of tables have to be after all values
+    #[serde(default, skip_serializing_if = "Vec::is_empty")] pub matchers: Vec<Matcher>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")] pub content_matchers: Vec<Matcher>,
     pub settings: HashMap<String, String>,
 }
 
+impl Style {
+    // Get the condition of the `@-moz-document` rule
+    // Conditions which could not be converted to matchers are used as they are
+    pub fn condition(&self) -> Option<String> {
+        domain::condition(&self.matchers).or_else(|| self.domain.clone())
+    }
+}
+
+// Information about the last download of a remote style
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct HttpMetadata {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
 // Used for serde to set the default of `enabled`
 // Required for backwards compatibility
 fn default_true() -> bool {
     true
 }
 
+// Used for serde to leave out the order of styles that have not been ordered yet
+fn is_zero(order: &u32) -> bool {
+    *order == 0
+}
+
+// Get the current time as seconds since the UNIX epoch
+pub fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
 // The type of a style
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum StyleType {
     Userstyle,
     Local,
     Remote,
+    Git,
 }
 
 // Check if the config file exists
@@ -216,6 +462,11 @@ pub fn restore_config(backup: &Config, error: &Error) -> Result<()> {
     }
 }
 
+// Check if a name only consists of digits, so it could be mistaken for an id
+fn is_number(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_digit())
+}
+
 // Create a new config file
 pub fn create_config() -> Result<()> {
     // Get the profile.ini file
@@ -321,6 +572,20 @@ fn config_path() -> Result<PathBuf> {
     Ok(path)
 }
 
+// Get path of the directory for files created by the user through RUM
+pub fn data_path() -> Result<PathBuf> {
+    let mut path = env::home_dir().ok_or("Unable to find home directory.")?;
+    path.push(DATA_PATH);
+    Ok(path)
+}
+
+// Get path of the directory for files managed by RUM
+pub fn cache_path() -> Result<PathBuf> {
+    let mut path = env::home_dir().ok_or("Unable to find home directory.")?;
+    path.push(CACHE_PATH);
+    Ok(path)
+}
+
 
 ////////// TESTS //////////
 
@@ -348,6 +613,15 @@ pub fn dummy_style() -> Style {
     Style {
         id: 0,
         domain: None,
+        matchers: Vec::new(),
+        content_domain: None,
+        content_matchers: Vec::new(),
+        tags: Vec::new(),
+        order: 0,
+        updated: None,
+        css_override: None,
+        http: None,
+        commit: None,
         enabled: true,
         uri: String::new(),
         name: String::new(),
@@ -499,7 +773,7 @@ fn remove_style__with_id_one__removes_style_one() {
     style_two.id = 2;
     let mut config = dummy_config(vec![style_zero, style_one, style_two]);
 
-    config.remove_style("1");
+    config.remove_style("1").unwrap();
 
     assert_eq!(config.styles.len(), 2);
     assert_eq!(config.styles[0].id, 0);
@@ -541,6 +815,158 @@ fn style_id_by_name__with_name_one__returns_one() {
     assert_eq!(id, 1);
 }
 
+#[test]
+#[allow(non_snake_case)]
+fn find_style__with_name_one__returns_style_one() {
+    let mut style_zero = dummy_style();
+    let mut style_one = dummy_style();
+    style_zero.name = String::from("zero");
+    style_one.name = String::from("one");
+    style_one.id = 1;
+    let config = dummy_config(vec![style_zero, style_one]);
+
+    let style = config.find_style("one").unwrap();
+
+    assert_eq!(style.id, 1);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn style_id_from_str__with_duplicate_name__returns_error() {
+    let mut style_zero = dummy_style();
+    let mut style_one = dummy_style();
+    style_zero.name = String::from("Dark");
+    style_one.name = String::from("Dark");
+    style_one.id = 1;
+    let config = dummy_config(vec![style_zero, style_one]);
+
+    let result = config.style_id_from_str("Dark");
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn style_id_from_str__with_digit_name__returns_style_with_name() {
+    let mut style_zero = dummy_style();
+    style_zero.name = String::from("1984");
+    let config = dummy_config(vec![style_zero]);
+
+    let id = config.style_id_from_str("1984").unwrap();
+
+    assert_eq!(id, 0);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn style_id_from_str__with_unique_prefix__returns_style() {
+    let mut style_zero = dummy_style();
+    let mut style_one = dummy_style();
+    style_zero.name = String::from("GitHub Dark");
+    style_one.name = String::from("Gmail Dark");
+    style_one.id = 1;
+    let config = dummy_config(vec![style_zero, style_one]);
+
+    let id = config.style_id_from_str("gm").unwrap();
+
+    assert_eq!(id, 1);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn style_id_from_str__with_ambiguous_prefix__returns_error() {
+    let mut style_zero = dummy_style();
+    let mut style_one = dummy_style();
+    style_zero.name = String::from("GitHub Dark");
+    style_one.name = String::from("Gmail Dark");
+    style_one.id = 1;
+    let config = dummy_config(vec![style_zero, style_one]);
+
+    let result = config.style_id_from_str("g");
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn expand_tags__with_tag__returns_ids_of_tagged_styles() {
+    let mut style_zero = dummy_style();
+    let mut style_one = dummy_style();
+    let mut style_two = dummy_style();
+    style_zero.tags = vec![String::from("dark")];
+    style_one.id = 1;
+    style_two.id = 2;
+    style_two.tags = vec![String::from("work"), String::from("dark")];
+    let config = dummy_config(vec![style_zero, style_one, style_two]);
+
+    let styles = vec![String::from("@dark"), String::from("one")];
+    let result = config.expand_tags(styles).unwrap();
+
+    assert_eq!(result, vec!["0", "2", "one"]);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn style_id_from_str__with_tag_of_two_styles__returns_error() {
+    let mut style_zero = dummy_style();
+    let mut style_one = dummy_style();
+    style_zero.tags = vec![String::from("dark")];
+    style_one.id = 1;
+    style_one.tags = vec![String::from("dark")];
+    let config = dummy_config(vec![style_zero, style_one]);
+
+    let result = config.style_id_from_str("@dark");
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn unique_name__with_taken_name__appends_number() {
+    let mut style_zero = dummy_style();
+    let mut style_one = dummy_style();
+    style_zero.name = String::from("Dark");
+    style_one.name = String::from("Dark (2)");
+    style_one.id = 1;
+    let config = dummy_config(vec![style_zero, style_one]);
+
+    let name = config.unique_name("Dark");
+
+    assert_eq!(name, "Dark (3)");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn unique_name__with_number__appends_number() {
+    let config = dummy_config(Vec::new());
+
+    let name = config.unique_name("5");
+
+    assert_eq!(name, "5 (2)");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn unique_name__with_tag__removes_at() {
+    let config = dummy_config(Vec::new());
+
+    let name = config.unique_name("@dark");
+
+    assert_eq!(name, "dark");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn next_style_id__with_number_as_name__skips_name() {
+    let mut style_zero = dummy_style();
+    style_zero.name = String::from("1");
+    let config = dummy_config(vec![style_zero]);
+
+    let id = config.next_style_id();
+
+    assert_eq!(id, 2);
+}
+
 #[test]
 #[allow(non_snake_case)]
 fn contains_style__with_style__returns_true() {
@@ -615,6 +1041,14 @@ fn config_path__returns_pathbuf_ending_rumconfig() {
     assert!(pathbuf.to_str().unwrap().ends_with("/.config/rum.toml"));
 }
 
+#[test]
+#[allow(non_snake_case)]
+fn cache_path__returns_pathbuf_ending_rumcache() {
+    let pathbuf = cache_path().unwrap();
+
+    assert!(pathbuf.to_str().unwrap().ends_with("/.cache/rum"));
+}
+
 #[test]
 #[allow(non_snake_case)]
 fn new__with_path__returns_config_with_path() {
@@ -657,3 +1091,190 @@ fn restore_config__with_style__writes_style() {
     let content = String::from_utf8_lossy(&(*writer));
     assert_eq!(content, expected);
 }
+
+#[test]
+#[allow(non_snake_case)]
+fn migrate__with_local_style_and_url__changes_type_to_remote() {
+    let mut style = dummy_style();
+    style.uri = String::from("https://example.com/style.css");
+    let mut config = dummy_config(vec![style]);
+
+    config.migrate();
+
+    assert_eq!(config.styles[0].style_type, StyleType::Remote);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn migrate__with_local_style_and_path__keeps_type_local() {
+    let mut style = dummy_style();
+    style.uri = String::from("/home/user/style.css");
+    let mut config = dummy_config(vec![style]);
+
+    config.migrate();
+
+    assert_eq!(config.styles[0].style_type, StyleType::Local);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn check_name__with_name_of_other_style__returns_error() {
+    let mut style_zero = dummy_style();
+    let mut style_one = dummy_style();
+    style_zero.name = String::from("Dark");
+    style_one.id = 1;
+    let config = dummy_config(vec![style_zero, style_one]);
+
+    let result = config.check_name("Dark", Some(1));
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn check_name__with_own_name__returns_ok() {
+    let mut style = dummy_style();
+    style.name = String::from("Dark");
+    let config = dummy_config(vec![style]);
+
+    let result = config.check_name("Dark", Some(0));
+
+    assert!(result.is_ok());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn check_name__with_number__returns_error() {
+    let config = dummy_config(vec![dummy_style()]);
+
+    let result = config.check_name("5", Some(0));
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn check_name__with_tag__returns_error() {
+    let config = dummy_config(vec![dummy_style()]);
+
+    let result = config.check_name("@dark", Some(0));
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn migrate__with_duplicate_names__makes_names_unique() {
+    let mut style_zero = dummy_style();
+    let mut style_one = dummy_style();
+    style_zero.name = String::from("Dark");
+    style_one.name = String::from("Dark");
+    style_one.id = 1;
+    let mut config = dummy_config(vec![style_zero, style_one]);
+
+    config.migrate();
+
+    assert_eq!(config.styles[0].name, "Dark");
+    assert_eq!(config.styles[1].name, "Dark (2)");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn migrate__with_legacy_domain__converts_domain_to_matchers() {
+    let mut style = dummy_style();
+    style.domain = Some(String::from("domain(\"kernel.org\"), url-prefix(\"https://lwn.net/\")"));
+    let mut config = dummy_config(vec![style]);
+
+    config.migrate();
+
+    assert_eq!(config.styles[0].domain, None);
+    assert_eq!(config.styles[0].matchers.len(), 2);
+    assert_eq!(
+        config.styles[0].condition(),
+        Some(String::from("domain(\"kernel.org\"), url-prefix(\"https://lwn.net/\")"))
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn migrate__without_order__orders_styles_by_id() {
+    let style_zero = dummy_style();
+    let mut style_one = dummy_style();
+    style_one.id = 1;
+    let mut config = dummy_config(vec![style_one, style_zero]);
+
+    config.migrate();
+
+    let orders: Vec<(i32, u32)> = config.styles.iter().map(|s| (s.id, s.order)).collect();
+    assert_eq!(orders, vec![(0, 1), (1, 2)]);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn migrate__without_order__orders_styles_like_target_file() {
+    let path = PathBuf::from("/rum/config/migrate_order.css");
+    let content = "\n/* RUM START 2 */\ntwo\n/* RUM END 2 */\n\
+                   \n/* RUM START 0 */\nzero\n/* RUM END 0 */\n";
+    dry_run::write(&path, content).unwrap();
+    let mut styles = Vec::new();
+    for &id in &[0, 1, 2] {
+        let mut style = dummy_style();
+        style.id = id;
+        style.path = path.clone();
+        styles.push(style);
+    }
+    let mut config = dummy_config(styles);
+
+    config.migrate();
+
+    let orders: Vec<(i32, u32)> = config.styles.iter().map(|s| (s.id, s.order)).collect();
+    assert_eq!(orders, vec![(2, 1), (0, 2), (1, 3)]);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn ids_after__with_styles_in_two_files__returns_later_styles_in_same_file() {
+    let mut style_zero = dummy_style();
+    let mut style_one = dummy_style();
+    let mut style_two = dummy_style();
+    style_zero.order = 2;
+    style_one.id = 1;
+    style_one.order = 3;
+    style_two.id = 2;
+    style_two.order = 4;
+    style_two.path = PathBuf::from("userChrome.css");
+    let config = dummy_config(vec![style_zero.clone(), style_one, style_two]);
+
+    let ids = config.ids_after(&style_zero);
+
+    assert_eq!(ids, vec![1]);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn migrate__with_unparsable_domain__keeps_domain() {
+    let mut style = dummy_style();
+    style.domain = Some(String::from("media-document(\"image\")"));
+    let mut config = dummy_config(vec![style]);
+
+    config.migrate();
+
+    assert_eq!(config.styles[0].condition(), Some(String::from("media-document(\"image\")")));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn write__with_matchers__roundtrips_matchers() {
+    let mut style = dummy_style();
+    style.updated = Some(1500000000);
+    style.tags = vec![String::from("dark")];
+    style.matchers = domain::parse_input("github.com").unwrap();
+    let config = dummy_config(vec![style]);
+
+    let output = toml::to_string(&config).unwrap();
+    let result = toml::from_str::<Config>(&output).unwrap();
+
+    assert!(output.contains("kind = \"domain\""));
+    assert_eq!(result.styles[0].matchers, config.styles[0].matchers);
+    assert_eq!(result.styles[0].tags, config.styles[0].tags);
+}
@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, HashMap};
+use css::{self, Token, TokenKind};
+use domain::{self, Matcher};
+use config::Config;
+use errors::*;
+use target;
+
+// A property that is set with `!important` by a style
+#[derive(Debug, PartialEq)]
+struct Declaration {
+    selector: String,
+    property: String,
+    scope: Vec<Matcher>,
+}
+
+// Two styles setting the same property for the same selector
+#[derive(Debug, PartialEq)]
+struct Conflict {
+    winner: i32,
+    loser: i32,
+    selector: String,
+    property: String,
+}
+
+// The kind of block that is opened by a `{`
+enum Frame {
+    // `@-moz-document`, an empty scope applies to all URLs
+    Scope(Vec<Matcher>),
+    // At-rules like `@media` which don't change the URLs
+    Transparent,
+    // A rule with its selectors
+    Rule(Vec<String>),
+    // Everything that can't contain conflicting declarations
+    Ignored,
+}
+
+pub fn run() -> Result<()> {
+    let config = Config::load()?;
+    let names: HashMap<i32, &str> = config
+        .styles
+        .iter()
+        .map(|s| (s.id, s.name.as_str()))
+        .collect();
+
+    // Styles can only conflict with other styles in the same file
+    let mut paths: Vec<_> = config.styles.iter().map(|s| s.path.clone()).collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut count = 0;
+    for path in paths {
+        let content = target::read(&path)?;
+
+        // Blocks later in the file win, so the file order is used instead of the config
+        let blocks: Vec<(i32, Vec<Declaration>)> = target::block_ids(&content)
+            .into_iter()
+            .filter(|id| names.contains_key(id))
+            .filter_map(|id| Some((id, declarations(target::find_block(&content, id)?))))
+            .collect();
+
+        // Group the conflicts by the styles involved
+        let mut pairs: BTreeMap<(i32, i32), Vec<String>> = BTreeMap::new();
+        for conflict in conflicts(&blocks) {
+            let rule = format!("{} {{ {} }}", conflict.selector, conflict.property);
+            pairs.entry((conflict.winner, conflict.loser)).or_default().push(rule);
+        }
+
+        for ((winner, loser), rules) in pairs {
+            println!(
+                "Style '{}' ({}) overrides '{}' ({}) in '{}':",
+                names[&winner],
+                winner,
+                names[&loser],
+                loser,
+                path.to_string_lossy()
+            );
+            for rule in &rules {
+                println!("    {}", rule);
+            }
+            println!();
+            count += rules.len();
+        }
+    }
+
+    match count {
+        0 => println!("No conflicts found"),
+        1 => println!("Found 1 conflict"),
+        _ => println!("Found {} conflicts", count),
+    }
+
+    Ok(())
+}
+
+// Find all properties set with `!important` by two styles for URLs they both apply to
+fn conflicts(blocks: &[(i32, Vec<Declaration>)]) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    for (i, &(loser, ref earlier)) in blocks.iter().enumerate() {
+        for &(winner, ref later) in &blocks[i + 1..] {
+            for declaration in earlier {
+                let overridden = later.iter().any(|d| {
+                    d.selector == declaration.selector && d.property == declaration.property
+                        && overlaps(&d.scope, &declaration.scope)
+                });
+
+                let conflict = Conflict {
+                    winner,
+                    loser,
+                    selector: declaration.selector.clone(),
+                    property: declaration.property.clone(),
+                };
+                if overridden && !conflicts.contains(&conflict) {
+                    conflicts.push(conflict);
+                }
+            }
+        }
+    }
+    conflicts
+}
+
+// Check if two scopes can apply to the same URL
+fn overlaps(first: &[Matcher], second: &[Matcher]) -> bool {
+    if first.is_empty() || second.is_empty() {
+        return true;
+    }
+
+    first.iter().any(|a| second.iter().any(|b| a.overlaps(b)))
+}
+
+// Get all declarations with `!important` and the scope they apply to
+fn declarations(css: &str) -> Vec<Declaration> {
+    let tokens: Vec<Token> = css::tokenize(css)
+        .into_iter()
+        .filter(|t| t.kind != TokenKind::Comment)
+        .collect();
+
+    let mut declarations = Vec::new();
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut prelude: Vec<&Token> = Vec::new();
+    for token in &tokens {
+        match token.kind {
+            TokenKind::OpenCurly => {
+                let frame = match frames.last() {
+                    Some(&Frame::Rule(_)) | Some(&Frame::Ignored) => Frame::Ignored,
+                    _ => frame(&prelude),
+                };
+                frames.push(frame);
+                prelude.clear();
+            }
+            TokenKind::CloseCurly | TokenKind::Semicolon => {
+                if let Some(Frame::Rule(selectors)) = frames.last() {
+                    if let Some(property) = important_property(&prelude) {
+                        let scope = scope(&frames);
+                        for selector in selectors {
+                            declarations.push(Declaration {
+                                selector: selector.clone(),
+                                property: property.clone(),
+                                scope: scope.clone(),
+                            });
+                        }
+                    }
+                }
+                if token.kind == TokenKind::CloseCurly {
+                    frames.pop();
+                }
+                prelude.clear();
+            }
+            _ => prelude.push(token),
+        }
+    }
+
+    declarations
+}
+
+// Get the kind of block from the tokens before its `{`
+fn frame(prelude: &[&Token]) -> Frame {
+    let mut tokens = prelude.iter().filter(|t| t.kind != TokenKind::Whitespace);
+    let first = match tokens.next() {
+        Some(first) => first,
+        None => return Frame::Ignored,
+    };
+
+    if first.kind != TokenKind::AtKeyword {
+        return Frame::Rule(selectors(prelude));
+    }
+
+    match first.text[1..].to_lowercase().as_str() {
+        "-moz-document" | "document" => {
+            let condition: String = tokens.map(|t| t.text).collect::<Vec<_>>().join(" ");
+            // Unknown conditions are treated like global styles, they might apply everywhere
+            let matchers = domain::parse_condition(&condition).and_then(|m| m.ok());
+            Frame::Scope(matchers.unwrap_or_default())
+        }
+        "media" | "supports" => Frame::Transparent,
+        _ => Frame::Ignored,
+    }
+}
+
+// Get the URLs the innermost `@-moz-document` applies to
+fn scope(frames: &[Frame]) -> Vec<Matcher> {
+    for frame in frames.iter().rev() {
+        if let Frame::Scope(ref matchers) = *frame {
+            return matchers.clone();
+        }
+    }
+    Vec::new()
+}
+
+// Split the prelude of a rule into its selectors, with normalized whitespace
+fn selectors(prelude: &[&Token]) -> Vec<String> {
+    let mut selectors = vec![String::new()];
+    let mut depth = 0;
+    for token in prelude {
+        match token.kind {
+            TokenKind::Function | TokenKind::OpenParen | TokenKind::OpenSquare => depth += 1,
+            TokenKind::CloseParen | TokenKind::CloseSquare => depth -= 1,
+            TokenKind::Comma if depth == 0 => {
+                selectors.push(String::new());
+                continue;
+            }
+            _ => (),
+        }
+        if let Some(selector) = selectors.last_mut() {
+            selector.push_str(token.text);
+        }
+    }
+
+    selectors
+        .iter()
+        .map(|s| s.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+// Get the name of a property, if the declaration is `!important`
+fn important_property(declaration: &[&Token]) -> Option<String> {
+    let tokens: Vec<&&Token> = declaration
+        .iter()
+        .filter(|t| t.kind != TokenKind::Whitespace)
+        .collect();
+
+    let property = tokens.len() >= 4 && tokens[0].kind == TokenKind::Ident;
+    if !property || tokens[1].kind != TokenKind::Colon {
+        return None;
+    }
+
+    let (bang, important) = (tokens[tokens.len() - 2], tokens[tokens.len() - 1]);
+    if bang.kind == TokenKind::Delim && bang.text == "!"
+        && important.text.eq_ignore_ascii_case("important")
+    {
+        Some(tokens[0].text.to_lowercase())
+    } else {
+        None
+    }
+}
+
+
+////////// TESTS //////////
+
+
+#[cfg(test)]
+use domain::Kind;
+
+#[cfg(test)]
+fn declaration(selector: &str, property: &str, scope: Vec<Matcher>) -> Declaration {
+    Declaration {
+        selector: selector.to_owned(),
+        property: property.to_owned(),
+        scope,
+    }
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn declarations__with_important_properties__returns_only_important() {
+    let css = "a,\n  b  .c { color: red !important; margin: 0 }";
+
+    let result = declarations(css);
+
+    assert_eq!(
+        result,
+        vec![declaration("a", "color", Vec::new()), declaration("b .c", "color", Vec::new())]
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn declarations__with_document_rule__returns_scope() {
+    let css = "@-moz-document domain(\"github.com\") {\n\
+               @media screen { a { color: red !important } }\n}";
+
+    let result = declarations(css);
+
+    let scope = vec![Matcher::new(Kind::Domain, "github.com").unwrap()];
+    assert_eq!(result, vec![declaration("a", "color", scope)]);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn declarations__with_keyframes__ignores_keyframes() {
+    let css = "@keyframes spin { to { color: red !important } }";
+
+    let result = declarations(css);
+
+    assert!(result.is_empty());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn conflicts__with_same_property_and_overlapping_scope__later_style_wins() {
+    let github = vec![Matcher::new(Kind::Domain, "github.com").unwrap()];
+    let gist = vec![Matcher::new(Kind::UrlPrefix, "https://gist.github.com/").unwrap()];
+    let blocks = vec![
+        (4, vec![declaration("a", "color", github)]),
+        (2, vec![declaration("a", "color", gist)]),
+    ];
+
+    let result = conflicts(&blocks);
+
+    assert_eq!(
+        result,
+        vec![
+            Conflict {
+                winner: 2,
+                loser: 4,
+                selector: String::from("a"),
+                property: String::from("color"),
+            },
+        ]
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn conflicts__with_different_domains__returns_empty() {
+    let github = vec![Matcher::new(Kind::Domain, "github.com").unwrap()];
+    let gitlab = vec![Matcher::new(Kind::Domain, "gitlab.com").unwrap()];
+    let blocks = vec![
+        (0, vec![declaration("a", "color", github)]),
+        (1, vec![declaration("a", "color", gitlab)]),
+    ];
+
+    let result = conflicts(&blocks);
+
+    assert!(result.is_empty());
+}
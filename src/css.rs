@@ -0,0 +1,729 @@
+use std::fmt;
+
+// The kind of a CSS token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Whitespace,
+    Comment,
+    String,
+    Ident,
+    Function,
+    AtKeyword,
+    Hash,
+    Number,
+    Url,
+    Colon,
+    Semicolon,
+    Comma,
+    OpenCurly,
+    CloseCurly,
+    OpenParen,
+    CloseParen,
+    OpenSquare,
+    CloseSquare,
+    Delim,
+    Cdo,
+    Cdc,
+    // Strings and URLs that have not been closed
+    BadString,
+    BadUrl,
+    // Comments that have not been closed
+    BadComment,
+}
+
+// A single CSS token with its position in the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub line: usize,
+    pub column: usize,
+}
+
+// Split CSS into tokens
+// Positions start at line 1 and column 1
+pub fn tokenize<'a>(css: &'a str) -> Vec<Token<'a>> {
+    let mut tokenizer = Tokenizer {
+        css,
+        pos: 0,
+        line: 1,
+        column: 1,
+    };
+
+    let mut tokens = Vec::new();
+    while let Some(token) = tokenizer.next_token() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+struct Tokenizer<'a> {
+    css: &'a str,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        let (start, line, column) = (self.pos, self.line, self.column);
+        let c = self.peek(0)?;
+
+        let kind = if is_whitespace(c) {
+            while self.peek_is(0, is_whitespace) {
+                self.advance();
+            }
+            TokenKind::Whitespace
+        } else if self.starts_with("/*") {
+            self.advance_by(2);
+            match self.css[self.pos..].find("*/") {
+                Some(end) => {
+                    self.advance_to(self.pos + end + 2);
+                    TokenKind::Comment
+                }
+                None => {
+                    self.advance_to(self.css.len());
+                    TokenKind::BadComment
+                }
+            }
+        } else if c == '"' || c == '\'' {
+            self.consume_string(c)
+        } else if c == '#' {
+            self.advance();
+            if self.peek_is(0, is_name) || self.is_escape(0) {
+                self.consume_name();
+                TokenKind::Hash
+            } else {
+                TokenKind::Delim
+            }
+        } else if self.starts_number() {
+            self.consume_number();
+            TokenKind::Number
+        } else if self.starts_with("<!--") {
+            self.advance_by(4);
+            TokenKind::Cdo
+        } else if self.starts_with("-->") {
+            self.advance_by(3);
+            TokenKind::Cdc
+        } else if c == '@' && self.starts_ident(1) {
+            self.advance();
+            self.consume_name();
+            TokenKind::AtKeyword
+        } else if self.starts_ident(0) {
+            self.consume_ident_like()
+        } else {
+            self.advance();
+            match c {
+                ':' => TokenKind::Colon,
+                ';' => TokenKind::Semicolon,
+                ',' => TokenKind::Comma,
+                '{' => TokenKind::OpenCurly,
+                '}' => TokenKind::CloseCurly,
+                '(' => TokenKind::OpenParen,
+                ')' => TokenKind::CloseParen,
+                '[' => TokenKind::OpenSquare,
+                ']' => TokenKind::CloseSquare,
+                _ => TokenKind::Delim,
+            }
+        };
+
+        Some(Token {
+            kind,
+            text: &self.css[start..self.pos],
+            line,
+            column,
+        })
+    }
+
+    // Consume a string, including both quotes
+    fn consume_string(&mut self, quote: char) -> TokenKind {
+        self.advance();
+        loop {
+            match self.peek(0) {
+                None => return TokenKind::BadString,
+                Some('\n') => return TokenKind::BadString,
+                Some('\\') => {
+                    self.advance();
+                    self.advance();
+                }
+                Some(c) => {
+                    self.advance();
+                    if c == quote {
+                        return TokenKind::String;
+                    }
+                }
+            }
+        }
+    }
+
+    // Consume an identifier, function or url
+    fn consume_ident_like(&mut self) -> TokenKind {
+        let start = self.pos;
+        self.consume_name();
+
+        if self.peek(0) != Some('(') {
+            return TokenKind::Ident;
+        }
+
+        let is_url = self.css[start..self.pos].eq_ignore_ascii_case("url");
+        self.advance();
+
+        // Quoted urls are regular functions
+        let mut offset = 0;
+        while self.peek_is(offset, is_whitespace) {
+            offset += 1;
+        }
+        let quoted = self.peek(offset) == Some('"') || self.peek(offset) == Some('\'');
+        if !is_url || quoted {
+            return TokenKind::Function;
+        }
+
+        loop {
+            match self.peek(0) {
+                None => return TokenKind::BadUrl,
+                Some(')') => {
+                    self.advance();
+                    return TokenKind::Url;
+                }
+                Some('\\') => {
+                    self.advance();
+                    self.advance();
+                }
+                Some(_) => self.advance(),
+            }
+        }
+    }
+
+    // Consume a number with an optional unit
+    fn consume_number(&mut self) {
+        if self.peek(0) == Some('+') || self.peek(0) == Some('-') {
+            self.advance();
+        }
+        while self.peek_is(0, |c| c.is_ascii_digit()) {
+            self.advance();
+        }
+        if self.peek(0) == Some('.') && self.peek_is(1, |c| c.is_ascii_digit()) {
+            self.advance();
+            while self.peek_is(0, |c| c.is_ascii_digit()) {
+                self.advance();
+            }
+        }
+
+        if self.starts_ident(0) {
+            self.consume_name();
+        } else if self.peek(0) == Some('%') {
+            self.advance();
+        }
+    }
+
+    // Consume all name characters and escapes
+    fn consume_name(&mut self) {
+        loop {
+            if self.is_escape(0) {
+                self.advance();
+                self.advance();
+            } else if self.peek_is(0, is_name) {
+                self.advance();
+            } else {
+                return;
+            }
+        }
+    }
+
+    // Check if an identifier starts at the offset
+    fn starts_ident(&self, offset: usize) -> bool {
+        match self.peek(offset) {
+            Some('-') => {
+                self.peek_is(offset + 1, |c| c == '-' || is_name_start(c)) ||
+                    self.is_escape(offset + 1)
+            }
+            Some('\\') => self.is_escape(offset),
+            Some(c) => is_name_start(c),
+            None => false,
+        }
+    }
+
+    // Check if a number starts at the current position
+    fn starts_number(&self) -> bool {
+        let digit = |offset| self.peek_is(offset, |c: char| c.is_ascii_digit());
+        match self.peek(0) {
+            Some('+') | Some('-') => digit(1) || (self.peek(1) == Some('.') && digit(2)),
+            Some('.') => digit(1),
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        }
+    }
+
+    // Check if there is a valid escape at the offset
+    fn is_escape(&self, offset: usize) -> bool {
+        self.peek(offset) == Some('\\') && self.peek_is(offset + 1, |c| c != '\n')
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        self.css[self.pos..].starts_with(text)
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.css[self.pos..].chars().nth(offset)
+    }
+
+    // Check if the character at the offset fulfills a condition
+    fn peek_is<F: Fn(char) -> bool>(&self, offset: usize, condition: F) -> bool {
+        match self.peek(offset) {
+            Some(c) => condition(c),
+            None => false,
+        }
+    }
+
+    // Move to the next character while keeping track of the position
+    fn advance(&mut self) {
+        if let Some(c) = self.peek(0) {
+            self.pos += c.len_utf8();
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+    }
+
+    fn advance_by(&mut self, count: usize) {
+        for _ in 0..count {
+            self.advance();
+        }
+    }
+
+    fn advance_to(&mut self, pos: usize) {
+        while self.pos < pos {
+            self.advance();
+        }
+    }
+}
+
+fn is_whitespace(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\n' || c == '\r' || c == '\x0c'
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || !c.is_ascii()
+}
+
+fn is_name(c: char) -> bool {
+    is_name_start(c) || c.is_ascii_digit() || c == '-'
+}
+
+// A syntax error with its position in the source
+#[derive(Debug, PartialEq)]
+pub struct SyntaxError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+// Check CSS for unclosed or unbalanced tokens
+pub fn validate(css: &str) -> Vec<SyntaxError> {
+    let mut errors = Vec::new();
+
+    // All brackets that have not been closed yet
+    let mut open: Vec<Token> = Vec::new();
+
+    for token in tokenize(css) {
+        let message = match token.kind {
+            TokenKind::BadString => Some("Unclosed string"),
+            TokenKind::BadComment => Some("Unclosed comment"),
+            TokenKind::BadUrl => Some("Unclosed url"),
+            _ => None,
+        };
+        if let Some(message) = message {
+            errors.push(SyntaxError {
+                message: message.to_owned(),
+                line: token.line,
+                column: token.column,
+            });
+            continue;
+        }
+
+        let expected = match token.kind {
+            TokenKind::OpenCurly |
+            TokenKind::OpenParen |
+            TokenKind::OpenSquare |
+            TokenKind::Function => {
+                open.push(token);
+                continue;
+            }
+            TokenKind::CloseCurly => TokenKind::OpenCurly,
+            TokenKind::CloseParen => TokenKind::OpenParen,
+            TokenKind::CloseSquare => TokenKind::OpenSquare,
+            _ => continue,
+        };
+
+        // Functions are closed by parentheses
+        let matches = match open.last() {
+            Some(last) if last.kind == TokenKind::Function => expected == TokenKind::OpenParen,
+            Some(last) => last.kind == expected,
+            None => false,
+        };
+
+        if matches {
+            open.pop();
+        } else {
+            errors.push(SyntaxError {
+                message: format!("Unexpected '{}'", token.text),
+                line: token.line,
+                column: token.column,
+            });
+        }
+    }
+
+    for token in open {
+        errors.push(SyntaxError {
+            message: format!("Unclosed '{}'", token.text),
+            line: token.line,
+            column: token.column,
+        });
+    }
+
+    errors
+}
+
+// Close all unclosed comments, strings, urls and brackets
+// Returns `None` if there are closing brackets that don't match, since these can't be fixed
+pub fn repair(css: &str) -> Option<String> {
+    let mut repaired = String::with_capacity(css.len());
+
+    // All brackets that have not been closed yet
+    let mut open: Vec<TokenKind> = Vec::new();
+
+    for token in tokenize(css) {
+        repaired.push_str(token.text);
+
+        let expected = match token.kind {
+            TokenKind::BadString => {
+                repaired.push_str(&token.text[..1]);
+                continue;
+            }
+            TokenKind::BadUrl => {
+                repaired.push(')');
+                continue;
+            }
+            TokenKind::BadComment => {
+                repaired.push_str("*/");
+                continue;
+            }
+            TokenKind::OpenCurly |
+            TokenKind::OpenParen |
+            TokenKind::OpenSquare |
+            TokenKind::Function => {
+                open.push(token.kind);
+                continue;
+            }
+            TokenKind::CloseCurly => TokenKind::OpenCurly,
+            TokenKind::CloseParen => TokenKind::OpenParen,
+            TokenKind::CloseSquare => TokenKind::OpenSquare,
+            _ => continue,
+        };
+
+        // Functions are closed by parentheses
+        match open.pop() {
+            Some(TokenKind::Function) if expected == TokenKind::OpenParen => (),
+            Some(kind) if kind == expected => (),
+            _ => return None,
+        }
+    }
+
+    for kind in open.iter().rev() {
+        repaired.push(match *kind {
+            TokenKind::OpenCurly => '}',
+            TokenKind::OpenSquare => ']',
+            _ => ')',
+        });
+    }
+
+    Some(repaired)
+}
+
+// At-rules which contain other rules instead of declarations
+const GROUPING_RULES: &[&str] = &[
+    "@-moz-document",
+    "@document",
+    "@media",
+    "@supports",
+    "@layer",
+    "@container",
+];
+
+// Add terminal colors to CSS
+pub fn highlight(css: &str) -> String {
+    let tokens = tokenize(css);
+    let mut output = String::new();
+
+    // Every open block and if it contains declarations
+    let mut blocks: Vec<bool> = Vec::new();
+    let mut grouping_prelude = false;
+    let mut in_value = false;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let declarations = blocks.last().cloned().unwrap_or(false);
+
+        let color = match token.kind {
+            TokenKind::Comment | TokenKind::BadComment => Some("90"),
+            TokenKind::AtKeyword => {
+                grouping_prelude = GROUPING_RULES.contains(&token.text.to_lowercase().as_str());
+                Some("35")
+            }
+            TokenKind::OpenCurly => {
+                blocks.push(!grouping_prelude);
+                grouping_prelude = false;
+                in_value = false;
+                None
+            }
+            TokenKind::CloseCurly => {
+                blocks.pop();
+                in_value = false;
+                None
+            }
+            TokenKind::Semicolon => {
+                in_value = false;
+                None
+            }
+            TokenKind::Colon if declarations && !in_value => {
+                in_value = true;
+                None
+            }
+            TokenKind::String | TokenKind::BadString | TokenKind::Url | TokenKind::BadUrl => {
+                Some("32")
+            }
+            TokenKind::Number | TokenKind::Hash if in_value => Some("34"),
+            TokenKind::Delim if in_value && token.text == "!" => Some("31"),
+            TokenKind::Ident if in_value && token.text.eq_ignore_ascii_case("important") => {
+                Some("31")
+            }
+            TokenKind::Ident if declarations && !in_value => {
+                // Properties are always followed by a colon
+                let next = tokens[i + 1..]
+                    .iter()
+                    .find(|t| t.kind != TokenKind::Whitespace && t.kind != TokenKind::Comment);
+                match next {
+                    Some(next) if next.kind == TokenKind::Colon => Some("36"),
+                    _ => Some("33"),
+                }
+            }
+            TokenKind::Whitespace | TokenKind::Comma => None,
+            _ if !declarations => Some("33"),
+            _ => None,
+        };
+
+        match color {
+            Some(color) => output.push_str(&format!("\x1b[0;{}m{}\x1b[0m", color, token.text)),
+            None => output.push_str(token.text),
+        }
+    }
+
+    output
+}
+
+
+////////// TESTS //////////
+
+
+#[cfg(test)]
+fn kinds(css: &str) -> Vec<TokenKind> {
+    tokenize(css)
+        .iter()
+        .filter(|t| t.kind != TokenKind::Whitespace)
+        .map(|t| t.kind)
+        .collect()
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn tokenize__with_rule__returns_tokens() {
+    let result = kinds("a:hover { color: #fff !important; }");
+
+    assert_eq!(
+        result,
+        vec![
+            TokenKind::Ident,
+            TokenKind::Colon,
+            TokenKind::Ident,
+            TokenKind::OpenCurly,
+            TokenKind::Ident,
+            TokenKind::Colon,
+            TokenKind::Hash,
+            TokenKind::Delim,
+            TokenKind::Ident,
+            TokenKind::Semicolon,
+            TokenKind::CloseCurly,
+        ]
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn tokenize__with_moz_document__returns_at_keyword_and_function() {
+    let result = kinds("@-moz-document domain(\"kernel.org\") {}");
+
+    assert_eq!(
+        result,
+        vec![
+            TokenKind::AtKeyword,
+            TokenKind::Function,
+            TokenKind::String,
+            TokenKind::CloseParen,
+            TokenKind::OpenCurly,
+            TokenKind::CloseCurly,
+        ]
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn tokenize__with_unquoted_url__returns_url() {
+    let tokens = tokenize("url(data:image/png;base64,AAAA)");
+
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].kind, TokenKind::Url);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn tokenize__with_dimensions__returns_numbers() {
+    let result = kinds("-1.5em 50% +.3 -moz-box");
+
+    assert_eq!(
+        result,
+        vec![
+            TokenKind::Number,
+            TokenKind::Number,
+            TokenKind::Number,
+            TokenKind::Ident,
+        ]
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn tokenize__with_unclosed_comment__returns_bad_comment() {
+    let tokens = tokenize("a {} /* comment");
+
+    assert_eq!(tokens.last().unwrap().kind, TokenKind::BadComment);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn tokenize__with_newline_in_string__returns_bad_string() {
+    let result = kinds("content: \"foo\nbar\";");
+
+    assert_eq!(result[2], TokenKind::BadString);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn tokenize__with_escaped_quote__returns_single_string() {
+    let tokens = tokenize("'it\\'s'");
+
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].kind, TokenKind::String);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn tokenize__with_multiple_lines__returns_positions() {
+    let tokens = tokenize("a {\n  color: red;\n}");
+
+    let color = tokens.iter().find(|t| t.text == "color").unwrap();
+    assert_eq!((color.line, color.column), (2, 3));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn validate__with_valid_css__returns_no_errors() {
+    let result = validate("@-moz-document domain(\"x\") { a[href] { color: rgb(0, 0, 0); } }");
+
+    assert_eq!(result, Vec::new());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn validate__with_unclosed_brace__returns_position_of_brace() {
+    let result = validate("a {\n  color: red;\n\nb { color: blue; }");
+
+    assert_eq!(
+        result,
+        vec![
+            SyntaxError {
+                message: String::from("Unclosed '{'"),
+                line: 1,
+                column: 3,
+            },
+        ]
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn validate__with_unexpected_brace__returns_error() {
+    let result = validate("a { color: red; }}");
+
+    assert_eq!(result[0].message, "Unexpected '}'");
+    assert_eq!(result[0].column, 18);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn validate__with_unclosed_comment__returns_error() {
+    let result = validate("a {} /* b {}");
+
+    assert_eq!(result[0].message, "Unclosed comment");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn highlight__with_declaration__colors_property_and_value() {
+    let result = highlight("a { color: #fff; }");
+
+    assert_eq!(
+        result,
+        "\x1b[0;33ma\x1b[0m { \x1b[0;36mcolor\x1b[0m: \x1b[0;34m#fff\x1b[0m; }"
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn highlight__with_moz_document__colors_nested_selector() {
+    let result = highlight("@-moz-document domain(\"x\") { a {} }");
+
+    assert!(result.contains("{ \x1b[0;33ma\x1b[0m {}"));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn repair__with_unclosed_brackets__closes_them_in_order() {
+    let result = repair("@media print { a { color: rgb(0, 0, 0").unwrap();
+
+    assert_eq!(result, "@media print { a { color: rgb(0, 0, 0)}}");
+    assert!(validate(&result).is_empty());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn repair__with_unclosed_string_and_comment__closes_them() {
+    let result = repair("a { content: \"x;\n}\n/* truncated").unwrap();
+
+    assert_eq!(result, "a { content: \"x;\"\n}\n/* truncated*/");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn repair__with_unexpected_bracket__returns_none() {
+    let result = repair("a { color: red; }}");
+
+    assert_eq!(result, None);
+}
@@ -0,0 +1,387 @@
+use config::Config;
+use clap::ArgMatches;
+use errors::*;
+use dry_run;
+use target;
+use atty;
+use add;
+
+// Number of unchanged lines shown around every change
+const CONTEXT: usize = 3;
+
+// Maximum number of edits before giving up on finding a minimal diff
+const MAX_EDITS: usize = 1000;
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    // Fetching styles must not write anything, like cloning git repositories into the cache
+    dry_run::enable();
+
+    let styles = if matches.is_present("all") {
+        Config::load()?
+            .styles
+            .iter()
+            .map(|s| s.id.to_string())
+            .collect()
+    } else {
+        let styles = matches.values_of_lossy("STYLE").unwrap();
+        Config::load()?.expand_tags(styles)?
+    };
+
+    for style in styles {
+        println!();
+        diff_style(&style)?;
+    }
+
+    Ok(())
+}
+
+// Print the changes an update would make to a style
+fn diff_style(style: &str) -> Result<()> {
+    println!("Comparing '{}'", style);
+
+    // Get current style
+    let config = Config::load()?;
+    let current_style = config.find_style(style)?.clone();
+
+    // Get the CSS that is currently installed
+    let content = target::read(&current_style.path)?;
+    let installed = match target::find_block(&content, current_style.id) {
+        Some(css) => css.to_owned(),
+        None => {
+            if current_style.enabled {
+                error!("Unable to find style in target file");
+            }
+            String::new()
+        }
+    };
+
+    // Get the CSS an update would install
+    let user_chrome = current_style.path.ends_with("userChrome.css");
+    let (id, name) = (current_style.id, current_style.name.clone());
+    let uri = current_style.uri.clone();
+    let current_style = Some(current_style);
+    // Show the changes even if the new version could not be installed
+    let updated = add::fetch_style(&config, &uri, user_chrome, current_style, &[], id, true)?.css;
+
+    let old_name = format!("{} (installed)", name);
+    let new_name = format!("{} (updated)", name);
+    let diff = unified(&installed, &updated, &old_name, &new_name);
+    if diff.is_empty() {
+        println!("No changes");
+    } else {
+        print(&diff);
+    }
+
+    Ok(())
+}
+
+// Print a unified diff, colored if stdout is a terminal
+pub fn print(diff: &str) {
+    if !atty::is(atty::Stream::Stdout) {
+        print!("{}", diff);
+        return;
+    }
+
+    for line in diff.lines() {
+        if line.starts_with("+++") || line.starts_with("---") {
+            println!("\x1b[1m{}\x1b[0m", line);
+        } else if line.starts_with('+') {
+            println!("\x1b[0;32m{}\x1b[0m", line);
+        } else if line.starts_with('-') {
+            println!("\x1b[0;31m{}\x1b[0m", line);
+        } else if line.starts_with("@@") {
+            println!("\x1b[0;36m{}\x1b[0m", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+// Create a unified diff between two texts
+// Returns an empty string if both are equal
+pub fn unified(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let lines = diff_lines(&old_lines, &new_lines);
+
+    // Get the range of every hunk including context
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if let Line::Same(_) = *line {
+            continue;
+        }
+
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + CONTEXT + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(hunk) if hunk.1 >= start => hunk.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for (start, end) in hunks {
+        // Count the lines of both texts in front of the hunk
+        let (mut old_before, mut new_before) = (0, 0);
+        for line in &lines[..start] {
+            match *line {
+                Line::Same(_) => {
+                    old_before += 1;
+                    new_before += 1;
+                }
+                Line::Removed(_) => old_before += 1,
+                Line::Added(_) => new_before += 1,
+            }
+        }
+
+        let mut body = String::new();
+        let (mut old_len, mut new_len) = (0, 0);
+        for line in &lines[start..end] {
+            match *line {
+                Line::Same(text) => {
+                    old_len += 1;
+                    new_len += 1;
+                    body.push_str(&format!(" {}\n", text));
+                }
+                Line::Removed(text) => {
+                    old_len += 1;
+                    body.push_str(&format!("-{}\n", text));
+                }
+                Line::Added(text) => {
+                    new_len += 1;
+                    body.push_str(&format!("+{}\n", text));
+                }
+            }
+        }
+
+        output.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_before, old_len),
+            hunk_range(new_before, new_len)
+        ));
+        output.push_str(&body);
+    }
+
+    output
+}
+
+// Format the line range of a hunk
+// Empty ranges point to the line in front of them
+fn hunk_range(before: usize, len: usize) -> String {
+    if len == 0 {
+        format!("{},0", before)
+    } else {
+        format!("{},{}", before + 1, len)
+    }
+}
+
+// A single line of a diff
+#[derive(Debug, PartialEq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+// Get the differences between two lists of lines
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    // Skip the common start and end, most changes are small
+    let prefix = old.iter().zip(new).take_while(|&(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|&(a, b)| a == b)
+        .count();
+
+    let mut lines: Vec<Line> = old[..prefix].iter().map(|l| Line::Same(l)).collect();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+    match myers(old_mid, new_mid) {
+        Some(mut mid) => lines.append(&mut mid),
+        None => {
+            // Replace everything if there are too many changes
+            lines.extend(old_mid.iter().map(|l| Line::Removed(l)));
+            lines.extend(new_mid.iter().map(|l| Line::Added(l)));
+        }
+    }
+    lines.extend(old[old.len() - suffix..].iter().map(|l| Line::Same(l)));
+
+    lines
+}
+
+// Find the shortest edit script using Myers' algorithm
+// Returns `None` if there are more than `MAX_EDITS` changes
+fn myers<'a>(old: &[&'a str], new: &[&'a str]) -> Option<Vec<Line<'a>>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize;
+
+    // Furthest reaching x for every diagonal k, stored after every step d
+    let mut v = vec![0isize; 2 * max + 2];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut found = false;
+
+    'search: for d in 0..(max.min(MAX_EDITS) + 1) {
+        let d = d as isize;
+        let mut k = -d;
+        while k <= d {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+
+            // Follow the diagonal as long as the lines are equal
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+
+            if x >= n && y >= m {
+                trace.push(v[(offset - d) as usize..(offset + d + 1) as usize].to_vec());
+                found = true;
+                break 'search;
+            }
+            k += 2;
+        }
+        trace.push(v[(offset - d) as usize..(offset + d + 1) as usize].to_vec());
+    }
+
+    if !found {
+        return None;
+    }
+
+    // Walk back through the trace to collect the edits
+    let depth = trace.len() as isize - 1;
+    let mut lines = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..depth + 1).rev() {
+        let prev = &trace[(d - 1) as usize];
+        let get = |k: isize| prev[(k + d - 1) as usize];
+
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = get(prev_k);
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            lines.push(Line::Same(old[(x - 1) as usize]));
+            x -= 1;
+            y -= 1;
+        }
+
+        if x == prev_x {
+            lines.push(Line::Added(new[(y - 1) as usize]));
+        } else {
+            lines.push(Line::Removed(old[(x - 1) as usize]));
+        }
+
+        x = prev_x;
+        y = prev_y;
+    }
+
+    while x > 0 && y > 0 {
+        lines.push(Line::Same(old[(x - 1) as usize]));
+        x -= 1;
+        y -= 1;
+    }
+
+    lines.reverse();
+    Some(lines)
+}
+
+
+////////// TESTS //////////
+
+
+#[test]
+#[allow(non_snake_case)]
+fn unified__with_equal_text__returns_empty_string() {
+    let text = "a {\n    color: red;\n}";
+
+    let result = unified(text, text, "old", "new");
+
+    assert_eq!(result, "");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn unified__with_changed_line__returns_hunk_with_context() {
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9";
+    let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9";
+
+    let result = unified(old, new, "old", "new");
+
+    assert_eq!(
+        result,
+        "--- old\n+++ new\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn unified__with_distant_changes__returns_two_hunks() {
+    let old = "a\n1\n2\n3\n4\n5\n6\n7\n8\nb";
+    let new = "A\n1\n2\n3\n4\n5\n6\n7\n8\nB";
+
+    let result = unified(old, new, "old", "new");
+
+    assert_eq!(
+        result,
+        "--- old\n+++ new\n@@ -1,4 +1,4 @@\n-a\n+A\n 1\n 2\n 3\n\
+         @@ -7,4 +7,4 @@\n 6\n 7\n 8\n-b\n+B\n"
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn unified__with_empty_old_text__returns_additions() {
+    let result = unified("", "a\nb", "old", "new");
+
+    assert_eq!(result, "--- old\n+++ new\n@@ -0,0 +1,2 @@\n+a\n+b\n");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn diff_lines__with_insertion_and_removal__returns_minimal_edits() {
+    let old = ["a", "b", "c", "d"];
+    let new = ["a", "c", "d", "e"];
+
+    let result = diff_lines(&old, &new);
+
+    assert_eq!(
+        result,
+        vec![
+            Line::Same("a"),
+            Line::Removed("b"),
+            Line::Same("c"),
+            Line::Same("d"),
+            Line::Added("e"),
+        ]
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn diff_lines__with_swapped_lines__returns_equal_line_count() {
+    let old = ["x", "a", "b", "y"];
+    let new = ["x", "b", "a", "y"];
+
+    let result = diff_lines(&old, &new);
+
+    assert_eq!(result[0], Line::Same("x"));
+    assert_eq!(result[4], Line::Same("y"));
+    assert_eq!(result.len(), 5);
+}
@@ -0,0 +1,158 @@
+use config::{Config, Style};
+use std::path::{Path, PathBuf};
+use std::io::Read;
+use std::fs::{self, File};
+use regex::Regex;
+use metadata::Metadata;
+use domain::{self, Matcher};
+use errors::*;
+use list;
+use uri;
+use add;
+
+// Extension of the files that contain the domain of a style
+const SIDECAR_EXTENSION: &str = ".domain";
+
+// Add every style in a directory or matching a glob
+pub fn add_all(pattern: &str, matchers: &[Matcher], user_chrome: bool, force: bool) -> Result<()> {
+    let files = find_files(pattern)?;
+    if files.is_empty() {
+        Err(format!("No styles found in '{}'", pattern))?;
+    }
+
+    for file in files {
+        println!();
+        println!("Adding '{}':", file.to_string_lossy());
+
+        let style = dir_style(&file, matchers, user_chrome, force)?;
+        let name = style.name.clone();
+        add::install_style(style, false)?;
+
+        println!("Added style '{}'", name);
+    }
+
+    Ok(())
+}
+
+// Create a style from a file, using its metadata instead of asking the user
+fn dir_style(file: &Path, matchers: &[Matcher], user_chrome: bool, force: bool) -> Result<Style> {
+    let mut css = String::new();
+    File::open(file)?.read_to_string(&mut css)?;
+
+    let metadata = Metadata::parse(&css);
+    let name = metadata.name.unwrap_or_else(|| style_name(file));
+
+    // The sidecar file takes precedence over the comment in the style
+    let domain = match sidecar_path(file) {
+        Some(ref sidecar) if sidecar.exists() => read_sidecar(sidecar)?,
+        _ => metadata.domain,
+    };
+
+    // Domains passed to the command are used for every style
+    let matchers = match domain {
+        _ if !matchers.is_empty() => matchers.to_vec(),
+        Some(domain) => domain::parse_input(&domain)?,
+        None => Vec::new(),
+    };
+
+    let config = Config::load()?;
+    let id = config.next_style_id();
+    let uri = file.to_string_lossy().into_owned();
+    let mut style = add::new_style(&config, id, uri, name, matchers, user_chrome);
+    style.css = add::checked_css(&style, &css, force)?;
+
+    Ok(style)
+}
+
+// Get all style files in a directory or matching a glob
+// Only the file name of a glob can contain wildcards
+fn find_files(pattern: &str) -> Result<Vec<PathBuf>> {
+    let path = uri::expand_home(pattern)?;
+
+    let (dir, name) = if path.is_dir() {
+        (path.clone(), None)
+    } else {
+        let name = path.file_name()
+            .ok_or_else(|| format!("Invalid directory or glob '{}'", pattern))?;
+        let name = Regex::new(&list::glob_to_regex(&name.to_string_lossy()))?;
+
+        // Files in the working directory don't have a parent
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_owned(),
+            _ => PathBuf::from("."),
+        };
+        (dir, Some(name))
+    };
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+
+        let matches = match name {
+            Some(ref name) => name.is_match(&file_name),
+            None => file_name.ends_with(".css"),
+        };
+
+        if matches && entry.path().is_file() {
+            files.push(entry.path().canonicalize()?);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+// Get the name of a style from its file name
+fn style_name(file: &Path) -> String {
+    let name = file.file_name().map(|n| n.to_string_lossy().into_owned());
+    let name = name.unwrap_or_default();
+
+    for extension in &[".user.css", ".css"] {
+        if name.ends_with(extension) {
+            return name[..name.len() - extension.len()].to_owned();
+        }
+    }
+
+    name
+}
+
+// Get the path of the file next to a style that contains its domain
+// The file for `dark.user.css` is `dark.domain`
+fn sidecar_path(file: &Path) -> Option<PathBuf> {
+    let dir = file.parent()?;
+    Some(dir.join([&style_name(file), SIDECAR_EXTENSION].concat()))
+}
+
+// Read the domain from a sidecar file
+fn read_sidecar(path: &Path) -> Result<Option<String>> {
+    let mut domain = String::new();
+    File::open(path)?.read_to_string(&mut domain)?;
+
+    let domain = domain.trim();
+    if domain.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(domain.to_owned()))
+    }
+}
+
+
+////////// TESTS //////////
+
+
+#[test]
+#[allow(non_snake_case)]
+fn style_name__with_user_css__strips_both_extensions() {
+    let result = style_name(Path::new("/themes/dark.user.css"));
+
+    assert_eq!(result, "dark");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn sidecar_path__with_user_css__returns_domain_file() {
+    let result = sidecar_path(Path::new("/themes/dark.user.css"));
+
+    assert_eq!(result, Some(PathBuf::from("/themes/dark.domain")));
+}
@@ -0,0 +1,56 @@
+use std::collections::BTreeMap;
+use config::Config;
+use errors::*;
+use target;
+use list;
+
+pub fn run() -> Result<()> {
+    let config = Config::load()?;
+
+    // Load every target file once
+    let mut contents = BTreeMap::new();
+    for style in &config.styles {
+        if !contents.contains_key(&style.path) {
+            contents.insert(style.path.clone(), target::read(&style.path)?);
+        }
+    }
+
+    let mut problems = Vec::new();
+
+    // Check that every style is installed correctly
+    for style in &config.styles {
+        let content = &contents[&style.path];
+        if let Some(problem) = list::problem(style, content) {
+            problems.push(format!("Style '{}' ({}): Block {}", style.name, style.id, problem));
+        } else if !style.enabled && target::find_block(content, style.id).is_some() {
+            problems.push(format!(
+                "Style '{}' ({}): Block is installed, but the style is disabled",
+                style.name,
+                style.id
+            ));
+        }
+    }
+
+    // Check for blocks which are not part of the config
+    for (path, content) in &contents {
+        for id in target::block_ids(content) {
+            if !config.styles.iter().any(|s| s.id == id && &s.path == path) {
+                problems.push(format!(
+                    "Unknown style {} in '{}'",
+                    id,
+                    path.to_string_lossy()
+                ));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        println!("No problems found");
+        return Ok(());
+    }
+
+    for problem in &problems {
+        error!("{}", problem);
+    }
+    Err(format!("Found {} problems", problems.len()))?
+}
@@ -0,0 +1,394 @@
+use config::{self, Config, Style};
+use css::{self, TokenKind};
+use clap::ArgMatches;
+use regex::Regex;
+use errors::*;
+use target;
+use add;
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let style = matches.value_of("STYLE").unwrap();
+    println!("Changing domain of '{}'", style);
+
+    // Load config and backup initial state
+    let mut config = Config::load()?;
+    let config_backup = config.clone();
+
+    let id = config.style_id_from_str(style)?;
+    let style = config
+        .styles
+        .iter_mut()
+        .find(|s| s.id == id)
+        .ok_or("Invalid style id or name")?;
+
+    // Get the new domain, without any domain the style applies to every URL
+    let old_condition = style.condition();
+    style.matchers = if matches.is_present("clear") {
+        Vec::new()
+    } else {
+        from_matches(matches)?
+    };
+    style.domain = None;
+
+    let style = style.clone();
+    config.write()?;
+
+    // Disabled styles get their new domain once they are enabled
+    if style.enabled {
+        if let Err(e) = reinstall(&style, old_condition) {
+            config::restore_config(&config_backup, &e)?;
+        }
+    }
+
+    let condition = style.condition().unwrap_or_else(|| String::from("all URLs"));
+    println!("Changed domain of '{}' to {}", style.name, condition);
+
+    Ok(())
+}
+
+// Replace the domain in the installed CSS of a style
+fn reinstall(style: &Style, old_condition: Option<String>) -> Result<()> {
+    let content = target::read(&style.path)?;
+    let block = target::find_block(&content, style.id)
+        .ok_or("Unable to find style in target file")?;
+
+    let css = add::replace_domain(block, old_condition.as_ref(), style.condition().as_ref())?;
+
+    let content = target::replace_block(&content, style.id, &css)
+        .ok_or("Unable to find style in target file")?;
+    target::write(&style.path, &content)
+}
+
+// The kind of URLs a style applies to, these are the `@-moz-document` functions
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    Domain,
+    Url,
+    UrlPrefix,
+    Regexp,
+}
+
+impl Kind {
+    // Get the name of the `@-moz-document` function
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Kind::Domain => "domain",
+            Kind::Url => "url",
+            Kind::UrlPrefix => "url-prefix",
+            Kind::Regexp => "regexp",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Kind> {
+        match name {
+            "domain" => Some(Kind::Domain),
+            "url" => Some(Kind::Url),
+            "url-prefix" => Some(Kind::UrlPrefix),
+            "regexp" => Some(Kind::Regexp),
+            _ => None,
+        }
+    }
+}
+
+// A single condition of the URLs a style applies to
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Matcher {
+    pub kind: Kind,
+    pub value: String,
+}
+
+impl Matcher {
+    // Create a new matcher, warning about regular expressions that might be invalid
+    pub fn new(kind: Kind, value: &str) -> Result<Matcher> {
+        if value.is_empty() {
+            Err(format!("Empty {} is not allowed", kind.name()))?;
+        }
+
+        // Firefox uses JavaScript regular expressions, which are only similar to Rust's
+        // Lookaheads and backreferences are valid in JavaScript, so this can't be an error
+        if kind == Kind::Regexp {
+            if let Err(e) = Regex::new(value) {
+                error!("Unable to check regexp '{}', it might not work in Firefox: {}", value, e);
+            }
+        }
+
+        Ok(Matcher {
+            kind,
+            value: value.to_owned(),
+        })
+    }
+
+    // Check if two matchers can apply to the same URL
+    // Regular expressions are only compared with single URLs, otherwise they might always overlap
+    pub fn overlaps(&self, other: &Matcher) -> bool {
+        let (a, b) = (self.value.as_str(), other.value.as_str());
+        match (self.kind, other.kind) {
+            (Kind::Domain, Kind::Domain) => in_domain(a, b) || in_domain(b, a),
+            (Kind::Domain, Kind::Url) | (Kind::Domain, Kind::UrlPrefix) => match host(b) {
+                Some(host) => in_domain(host, a),
+                None => true,
+            },
+            (Kind::Url, Kind::Url) => a == b,
+            (Kind::Url, Kind::UrlPrefix) => a.starts_with(b),
+            (Kind::UrlPrefix, Kind::UrlPrefix) => a.starts_with(b) || b.starts_with(a),
+            (Kind::Regexp, Kind::Url) => Regex::new(&format!("^(?:{})$", a))
+                .map(|regex| regex.is_match(b))
+                .unwrap_or(true),
+            (Kind::Url, Kind::Regexp) => other.overlaps(self),
+            (Kind::Regexp, _) | (_, Kind::Regexp) => true,
+            _ => other.overlaps(self),
+        }
+    }
+
+    // Get the `@-moz-document` function with the escaped value
+    pub fn to_css(&self) -> String {
+        let mut escaped = String::with_capacity(self.value.len());
+        for c in self.value.chars() {
+            match c {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\a "),
+                _ => escaped.push(c),
+            }
+        }
+        format!("{}(\"{}\")", self.kind.name(), escaped)
+    }
+}
+
+// Get the matchers passed as CLI parameters
+pub fn from_matches(matches: &ArgMatches) -> Result<Vec<Matcher>> {
+    // Domains also accept the `@-moz-document` syntax of older versions
+    let mut matchers = Vec::new();
+    for domain in matches.values_of("domain").into_iter().flatten() {
+        matchers.append(&mut parse_input(domain)?);
+    }
+
+    for kind in &[Kind::Url, Kind::UrlPrefix, Kind::Regexp] {
+        for value in matches.values_of(kind.name()).into_iter().flatten() {
+            matchers.push(Matcher::new(*kind, value)?);
+        }
+    }
+
+    Ok(matchers)
+}
+
+// Check if a host is a domain or one of its subdomains
+fn in_domain(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+// Get the host of a URL, if the URL contains all of it
+fn host(url: &str) -> Option<&str> {
+    let start = url.find("://")? + 3;
+    let end = start + url[start..].find(&['/', ':', '?', '#'][..])?;
+    Some(&url[start..end])
+}
+
+// Create the condition of the `@-moz-document` rule for a list of matchers
+pub fn condition(matchers: &[Matcher]) -> Option<String> {
+    if matchers.is_empty() {
+        return None;
+    }
+
+    let functions: Vec<String> = matchers.iter().map(Matcher::to_css).collect();
+    Some(functions.join(", "))
+}
+
+// Parse matchers entered by the user
+// This is either a list of domains like `kernel.org, github.com` or an `@-moz-document` condition
+pub fn parse_input(input: &str) -> Result<Vec<Matcher>> {
+    if input.contains('(') {
+        return parse_condition(input)
+            .ok_or_else(|| format!("Unable to parse '{}'", input))?;
+    }
+
+    input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|domain| !domain.is_empty())
+        .map(|domain| Matcher::new(Kind::Domain, domain))
+        .collect()
+}
+
+// Parse the condition of an `@-moz-document` rule like `domain("kernel.org"), url-prefix(…)`
+// Returns `None` if this is not a valid condition
+pub fn parse_condition(condition: &str) -> Option<Result<Vec<Matcher>>> {
+    let tokens = css::tokenize(condition);
+    let mut tokens = tokens
+        .iter()
+        .filter(|t| t.kind != TokenKind::Whitespace && t.kind != TokenKind::Comment);
+
+    let mut matchers = Vec::new();
+    while let Some(token) = tokens.next() {
+        let (kind, value) = match token.kind {
+            // Unquoted urls are a single token
+            TokenKind::Url => {
+                let value = &token.text[token.text.find('(')? + 1..token.text.len() - 1];
+                (Kind::Url, value.trim().to_owned())
+            }
+            TokenKind::Function => {
+                let kind = Kind::from_name(&token.text[..token.text.len() - 1].to_lowercase())?;
+
+                // The value is either a string or everything until the closing parenthesis
+                let mut value = String::new();
+                loop {
+                    let token = tokens.next()?;
+                    match token.kind {
+                        TokenKind::CloseParen => break,
+                        TokenKind::String => value.push_str(&unquote(token.text)),
+                        _ => value.push_str(token.text),
+                    }
+                }
+                (kind, value)
+            }
+            _ => return None,
+        };
+
+        matchers.push(Matcher::new(kind, &value));
+
+        // Functions are separated by commas
+        match tokens.next() {
+            Some(token) if token.kind == TokenKind::Comma => (),
+            Some(_) => return None,
+            None => break,
+        }
+    }
+
+    if matchers.is_empty() {
+        return None;
+    }
+
+    Some(matchers.into_iter().collect())
+}
+
+// Remove the quotes and escapes of a CSS string
+fn unquote(string: &str) -> String {
+    let mut chars = string[1..string.len() - 1].chars();
+    let mut unquoted = String::new();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            unquoted.extend(chars.next());
+        } else {
+            unquoted.push(c);
+        }
+    }
+    unquoted
+}
+
+
+////////// TESTS //////////
+
+
+#[test]
+#[allow(non_snake_case)]
+fn to_css__with_quote_and_backslash__escapes_value() {
+    let matcher = Matcher::new(Kind::Regexp, "https://a\\.org/\"").unwrap();
+
+    let result = matcher.to_css();
+
+    assert_eq!(result, "regexp(\"https://a\\\\.org/\\\"\")");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn new__with_javascript_lookahead__returns_matcher() {
+    let result = Matcher::new(Kind::Regexp, "https://a\\.org/(?!settings).*");
+
+    assert!(result.is_ok());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn condition__with_two_matchers__joins_with_comma() {
+    let matchers = vec![
+        Matcher::new(Kind::Domain, "github.com").unwrap(),
+        Matcher::new(Kind::UrlPrefix, "https://gist.github.com/").unwrap(),
+    ];
+
+    let result = condition(&matchers);
+
+    assert_eq!(
+        result,
+        Some(String::from("domain(\"github.com\"), url-prefix(\"https://gist.github.com/\")"))
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn parse_condition__with_quoted_and_unquoted_functions__returns_matchers() {
+    let condition = "domain(\"kernel.org\"), url(chrome://browser/content/browser.xul)";
+
+    let result = parse_condition(condition).unwrap().unwrap();
+
+    assert_eq!(
+        result,
+        vec![
+            Matcher::new(Kind::Domain, "kernel.org").unwrap(),
+            Matcher::new(Kind::Url, "chrome://browser/content/browser.xul").unwrap(),
+        ]
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn parse_condition__with_escaped_regexp__unescapes_value() {
+    let result = parse_condition("regexp(\"https://a\\\\.org/.*\")").unwrap().unwrap();
+
+    assert_eq!(result[0].value, "https://a\\.org/.*");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn parse_condition__with_unknown_function__returns_none() {
+    let result = parse_condition("media-document(\"image\")");
+
+    assert!(result.is_none());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn parse_input__with_plain_domains__returns_domain_matchers() {
+    let result = parse_input("kernel.org, github.com").unwrap();
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[1], Matcher::new(Kind::Domain, "github.com").unwrap());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn overlaps__with_subdomain__returns_true() {
+    let domain = Matcher::new(Kind::Domain, "github.com").unwrap();
+    let subdomain = Matcher::new(Kind::Domain, "gist.github.com").unwrap();
+
+    assert!(domain.overlaps(&subdomain));
+    assert!(subdomain.overlaps(&domain));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn overlaps__with_url_prefix_on_other_domain__returns_false() {
+    let domain = Matcher::new(Kind::Domain, "github.com").unwrap();
+    let prefix = Matcher::new(Kind::UrlPrefix, "https://gitlab.com/").unwrap();
+
+    assert!(!prefix.overlaps(&domain));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn overlaps__with_regexp_and_matching_url__returns_true() {
+    let regexp = Matcher::new(Kind::Regexp, "https://a\\.org/.*").unwrap();
+    let url = Matcher::new(Kind::Url, "https://a.org/b").unwrap();
+
+    assert!(url.overlaps(&regexp));
+    assert!(regexp.overlaps(&url));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn overlaps__with_regexp_and_other_url__returns_false() {
+    let regexp = Matcher::new(Kind::Regexp, "https://a\\.org/.*").unwrap();
+    let url = Matcher::new(Kind::Url, "https://b.org/a").unwrap();
+
+    assert!(!url.overlaps(&regexp));
+    assert!(!regexp.overlaps(&url));
+}
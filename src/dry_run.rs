@@ -0,0 +1,126 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::sync::Mutex;
+use std::{env, process};
+use errors::*;
+use diff;
+
+// Set when nothing should be written to disk
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+// Files that would have been written, with their original and new content
+lazy_static! {
+    static ref FILES: Mutex<BTreeMap<PathBuf, (String, String)>> = {
+        Mutex::new(BTreeMap::new())
+    };
+}
+
+// Stop all changes from being written to disk
+pub fn enable() {
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+// Check if this is a dry run
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+// Directory for copies of files which are only needed during a dry run
+pub fn temp_path() -> PathBuf {
+    env::temp_dir().join(format!("rum-dry-run-{}", process::id()))
+}
+
+// Create a directory, unless this is a dry run
+pub fn create_dir_all<P: AsRef<Path>>(path: P) -> Result<()> {
+    if !enabled() {
+        fs::create_dir_all(path)?;
+    }
+    Ok(())
+}
+
+// Get the content a file would have if the changes had been written
+pub fn read(path: &Path) -> Option<String> {
+    let files = FILES.lock().ok()?;
+    files.get(path).map(|file| file.1.clone())
+}
+
+// Record the new content of a file instead of writing it
+pub fn write(path: &Path, content: &str) -> Result<()> {
+    let mut files = FILES.lock().map_err(|_| "Unable to lift dry run lock")?;
+
+    // Keep the content that is currently on disk for comparison
+    let original = match files.remove(path) {
+        Some((original, _)) => original,
+        None => {
+            let mut original = String::new();
+            if path.exists() {
+                File::open(path)?.read_to_string(&mut original)?;
+            }
+            original
+        }
+    };
+
+    files.insert(path.to_owned(), (original, content.to_owned()));
+
+    Ok(())
+}
+
+// Remove the temporary directory of the dry run
+pub fn clean() {
+    let temp_path = temp_path();
+    if temp_path.exists() {
+        let _ = fs::remove_dir_all(temp_path);
+    }
+}
+
+// Print the changes to every file that would have been written
+pub fn report() -> Result<()> {
+    let files = FILES.lock().map_err(|_| "Unable to lift dry run lock")?;
+
+    println!("\nDry run, no files have been written");
+    for (path, file) in files.iter() {
+        let path = path.to_string_lossy();
+        let old_name = format!("{} (current)", path);
+        let new_name = format!("{} (dry run)", path);
+        let changes = diff::unified(&file.0, &file.1, &old_name, &new_name);
+
+        println!();
+        if changes.is_empty() {
+            println!("No changes to '{}'", path);
+        } else {
+            diff::print(&changes);
+        }
+    }
+
+    Ok(())
+}
+
+
+////////// TESTS //////////
+
+
+#[test]
+#[allow(non_snake_case)]
+fn read__after_write__returns_new_content() {
+    let path = Path::new("/rum/dry_run/read_after_write.css");
+
+    write(path, "first").unwrap();
+    write(path, "second").unwrap();
+
+    assert_eq!(read(path), Some(String::from("second")));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn write__with_missing_file__keeps_empty_original() {
+    let path = Path::new("/rum/dry_run/missing_original.css");
+
+    write(path, "first").unwrap();
+    write(path, "second").unwrap();
+
+    let files = FILES.lock().unwrap();
+    assert_eq!(files.get(path).unwrap().0, "");
+}
@@ -0,0 +1,156 @@
+use std::io::{self, BufRead, Read, Write};
+use config::{Config, StyleType};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::fs::{self, File};
+use clap::ArgMatches;
+use errors::*;
+use overrides;
+use dry_run;
+use std::env;
+use add;
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let style = matches.value_of("STYLE").unwrap();
+    edit_style(style)
+}
+
+// Edit the CSS of a style and install the changes
+fn edit_style(style: &str) -> Result<()> {
+    println!("Editing '{}'", style);
+
+    // Get the style from the config
+    let config = Config::load()?;
+    let style = config.find_style(style)?.clone();
+
+    // Local styles are edited at their source
+    // Everything else is edited in its override, so changes are kept when it is updated
+    let path = Path::new(&style.uri);
+    if style.style_type != StyleType::Local || !path.exists() {
+        println!("Changes are saved in the override of this style");
+        return overrides::change(style.id, false, None);
+    }
+
+    let css = edit_file(path)?;
+
+    // Disabled styles are installed from their source once they are enabled
+    if !style.enabled {
+        println!("Style is disabled, changes will be installed once it is enabled");
+        return Ok(());
+    }
+
+    add::reinstall(&style, &css)?;
+
+    println!("Edited style '{}'", style.name);
+
+    Ok(())
+}
+
+// Open a CSS file in the user's editor until it is valid
+// A copy is edited, so the file is only changed once the CSS is valid
+pub fn edit_file(path: &Path) -> Result<String> {
+    let copy = temp_copy(path)?;
+    let result = edit_copy(path, &copy);
+    if let Some(dir) = copy.parent() {
+        let _ = fs::remove_dir_all(dir);
+    }
+    let css = result?;
+
+    // A dry run only records the changes
+    if dry_run::enabled() {
+        dry_run::write(path, &css)?;
+    } else {
+        File::create(path)?.write_all(css.as_bytes())?;
+    }
+
+    Ok(css)
+}
+
+// Open the copy of a file in the editor until it can be compiled to valid CSS
+fn edit_copy(path: &Path, copy: &Path) -> Result<String> {
+    loop {
+        open_editor(copy)?;
+
+        let mut css = String::new();
+        File::open(copy)?.read_to_string(&mut css)?;
+
+        // SCSS, Less and Stylus are checked after compiling them
+        match add::compile_valid(&path.to_string_lossy(), &css) {
+            Ok(_) => return Ok(css),
+            Err(e) => error!("Error: {}", e),
+        }
+
+        if !edit_again(&mut io::stdin().lock()) {
+            Err("Style has not been changed")?;
+        }
+    }
+}
+
+// Copy a file to a temporary directory, keeping its name for the syntax highlighting of editors
+fn temp_copy(path: &Path) -> Result<PathBuf> {
+    let mut copy = env::temp_dir();
+    copy.push(format!("rum-edit-{}", process::id()));
+    fs::create_dir_all(&copy)?;
+    copy.push(path.file_name().ok_or("Invalid file path")?);
+
+    // Use the changes of previous operations during a dry run
+    let mut css = String::new();
+    if let Some(content) = dry_run::read(path) {
+        css = content;
+    } else if path.exists() {
+        File::open(path)?.read_to_string(&mut css)?;
+    }
+    File::create(&copy)?.write_all(css.as_bytes())?;
+
+    Ok(copy)
+}
+
+// Open a file in the user's editor and wait for it to be closed
+fn open_editor(path: &Path) -> Result<()> {
+    let editor = env::var("EDITOR").unwrap_or_else(|_| String::from("vi"));
+
+    // Allow arguments like `code --wait`
+    let mut args = editor.split_whitespace();
+    let program = args.next().ok_or("Invalid $EDITOR")?;
+
+    let status = Command::new(program).args(args).arg(path).status()?;
+    if !status.success() {
+        Err("Editor exited with an error")?;
+    }
+
+    Ok(())
+}
+
+// Ask the user if the file should be opened again
+fn edit_again<T: BufRead>(input: &mut T) -> bool {
+    print!("Edit again? [Y/n] > ");
+    let _ = io::stdout().flush();
+
+    let mut choice = String::new();
+    let _ = input.read_line(&mut choice);
+    choice.trim().to_lowercase() != "n"
+}
+
+
+////////// TESTS //////////
+
+
+#[test]
+#[allow(non_snake_case)]
+fn edit_again__with_empty_input__returns_true() {
+    let mut cursor = io::Cursor::new(b"\n");
+
+    let result = edit_again(&mut cursor);
+
+    assert!(result);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn edit_again__with_n__returns_false() {
+    let mut cursor = io::Cursor::new(b"N\n");
+
+    let result = edit_again(&mut cursor);
+
+    assert!(!result);
+}
@@ -0,0 +1,148 @@
+use std::collections::btree_map::{BTreeMap, Entry};
+use config::{self, Config, Style};
+use std::path::PathBuf;
+use clap::ArgMatches;
+use list::Filter;
+use errors::*;
+use remove;
+use target;
+use add;
+
+pub fn run(matches: &ArgMatches, enabled: bool) -> Result<()> {
+    let config = Config::load()?;
+    let force = matches.is_present("force");
+
+    // Get all selected styles, without any style the filter selects everything
+    let ids: Vec<i32> = match matches.values_of_lossy("STYLE") {
+        Some(styles) => config
+            .expand_tags(styles)?
+            .iter()
+            .map(|s| config.style_id_from_str(s))
+            .collect::<Result<_>>()?,
+        None => {
+            let filter = Filter::from_matches(matches)?;
+            config
+                .styles
+                .iter()
+                .filter(|s| filter.matches(s))
+                .map(|s| s.id)
+                .collect()
+        }
+    };
+
+    if ids.is_empty() {
+        Err("No styles selected")?;
+    }
+
+    let changes: Vec<(i32, bool)> = ids.into_iter().map(|id| (id, enabled)).collect();
+    set_enabled(&changes, force)
+}
+
+// Enable or disable multiple styles
+// Every target file is only written once
+pub fn set_enabled(changes: &[(i32, bool)], force: bool) -> Result<()> {
+    // Load config and backup initial state
+    let mut config = Config::load()?;
+    let config_backup = config.clone();
+
+    let mut backups = Vec::new();
+    let mut changed = Vec::new();
+    let mut contents: BTreeMap<PathBuf, String> = BTreeMap::new();
+    for &(id, enabled) in changes {
+        let style = config
+            .styles
+            .iter()
+            .find(|s| s.id == id)
+            .ok_or("Invalid style id or name")?
+            .clone();
+
+        // Nothing has to be done if the style is in the right state already
+        let state = if enabled { "enabled" } else { "disabled" };
+        if style.enabled == enabled {
+            println!("Style '{}' is already {}", style.name, state);
+            continue;
+        }
+
+        let content = match contents.entry(style.path.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let content = target::read(&style.path)?;
+                backups.push((style.path.clone(), content.clone()));
+                entry.insert(content)
+            }
+        };
+
+        // Disabled styles are not part of the target file
+        let style = if enabled {
+            let style = enable_style(&config, style, content, force)?;
+            config.styles.retain(|s| s.id != id);
+            config.styles.push(style.clone());
+            style
+        } else {
+            *content = remove::remove_style_from_str(content, id);
+            config.toggle_style(id)?;
+            style
+        };
+
+        changed.push(format!("Style '{}' has been {}", style.name, state));
+    }
+
+    config.write()?;
+
+    // Restore the config and every target file if any of them could not be written
+    let result = contents
+        .iter()
+        .map(|(path, content)| target::write(path, content))
+        .collect::<Result<Vec<()>>>();
+    if let Err(e) = result {
+        error!("Error: {}", e);
+        target::restore(&config_backup, &backups)?;
+        Err("No style has been changed")?;
+    }
+
+    for message in changed {
+        println!("{}", message);
+    }
+
+    Ok(())
+}
+
+// Fetch the CSS of a disabled style and add it to the content of its target file
+fn enable_style(config: &Config, style: Style, content: &mut String, force: bool) -> Result<Style> {
+    let user_chrome = style.path.ends_with("userChrome.css");
+    let (uri, id) = (style.uri.clone(), style.id);
+    let mut style = add::fetch_style(config, &uri, user_chrome, Some(style), &[], id, force)?;
+    style.enabled = true;
+    style.updated = Some(config::timestamp());
+
+    *content = install_block(content, id, &style.css, &config.ids_after(&style));
+
+    Ok(style)
+}
+
+// Add the CSS of a style to a target file at the position of the style
+fn install_block(content: &str, id: i32, css: &str, later: &[i32]) -> String {
+    // Remove leftovers of the style to make sure it's only installed once
+    let content = remove::remove_style_from_str(content, id);
+
+    target::insert_block(&content, id, css, later)
+}
+
+
+////////// TESTS //////////
+
+
+#[test]
+#[allow(non_snake_case)]
+fn install_block__with_leftover_block__installs_style_once() {
+    let content = "\n/* RUM START 0 */\nold\n/* RUM END 0 */\n\
+                   \n/* RUM START 1 */\none\n/* RUM END 1 */\n";
+
+    let result = install_block(content, 0, "new", &[]);
+
+    assert_eq!(
+        result,
+        "\n/* RUM START 1 */\none\n/* RUM END 1 */\n\
+         \n/* RUM START 0 */\nnew\n/* RUM END 0 */\n"
+    );
+}
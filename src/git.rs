@@ -0,0 +1,206 @@
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::io::Read;
+use std::fs::{self, File};
+use errors::*;
+use dry_run;
+use config;
+
+// Prefix of all URIs that point to a file in a git repository
+pub const PREFIX: &str = "git+";
+
+// A file inside of a git repository
+// The URI format is `git+<repository>#<path>@<ref>`
+#[derive(Debug, PartialEq)]
+pub struct Source {
+    pub repo: String,
+    pub path: String,
+    pub reference: Option<String>,
+}
+
+impl Source {
+    // Parse the URI of a git style
+    pub fn parse(uri: &str) -> Result<Source> {
+        if !uri.starts_with(PREFIX) {
+            Err(format!("'{}' is not a git URI", uri))?;
+        }
+
+        // The repository might contain `@`, so the ref is only searched in the file path
+        let mut parts = uri[PREFIX.len()..].splitn(2, '#');
+        let repo = parts.next().unwrap_or_default();
+        let file = parts.next().ok_or("Git URI is missing the file path after '#'")?;
+        let (path, reference) = match file.rfind('@') {
+            Some(index) => (&file[..index], Some(file[index + 1..].to_owned())),
+            None => (file, None),
+        };
+
+        if repo.is_empty() || path.is_empty() {
+            Err(format!("Invalid git URI '{}'", uri))?;
+        }
+
+        // The file has to be inside of the repository
+        let file_path = Path::new(path);
+        if file_path.is_absolute() || file_path.components().any(|c| c == Component::ParentDir) {
+            Err(format!("The path '{}' in '{}' is not inside of the repository", path, uri))?;
+        }
+
+        Ok(Source {
+            repo: repo.to_owned(),
+            path: path.to_owned(),
+            reference,
+        })
+    }
+
+    // Create the URI of this file
+    pub fn uri(&self) -> String {
+        let mut uri = [PREFIX, &self.repo, "#", &self.path].concat();
+        if let Some(ref reference) = self.reference {
+            uri.push('@');
+            uri.push_str(reference);
+        }
+        uri
+    }
+
+    // Get the latest version of the file
+    // Returns the CSS and the commit it has been taken from
+    pub fn fetch(&self) -> Result<(String, String)> {
+        // A dry run clones the repository into a temporary directory instead of the cache
+        let dir = if dry_run::enabled() {
+            let mut dir = dry_run::temp_path();
+            dir.push("git");
+            dir.push(dir_name(&self.repo));
+            dir
+        } else {
+            cache_dir(&self.repo)?
+        };
+
+        // Clone the repository once and only fetch the changes afterwards
+        if dir.join(".git").exists() {
+            git(&dir, &["fetch", "--quiet", "--tags", "--force", "--prune", "origin"])?;
+        } else {
+            fs::create_dir_all(&dir)?;
+            git(&dir, &["clone", "--quiet", "--", &self.repo, "."])?;
+        }
+
+        let commit = self.resolve(&dir)?;
+        git(&dir, &["checkout", "--quiet", "--detach", &commit])?;
+
+        let mut css = String::new();
+        File::open(dir.join(&self.path))
+            .map_err(|e| format!("Unable to open '{}' in '{}': {}", self.path, self.repo, e))?
+            .read_to_string(&mut css)?;
+
+        Ok((css, commit))
+    }
+
+    // Get the commit the ref is currently pointing to
+    // Branches are followed on the remote, so updates get new commits
+    fn resolve(&self, dir: &Path) -> Result<String> {
+        let candidates = match self.reference {
+            Some(ref reference) => vec![format!("origin/{}", reference), reference.clone()],
+            None => vec![String::from("origin/HEAD")],
+        };
+
+        for candidate in candidates {
+            let rev = [&candidate, "^{commit}"].concat();
+            if let Ok(commit) = git(dir, &["rev-parse", "--verify", "--quiet", &rev]) {
+                return Ok(commit);
+            }
+        }
+
+        let reference = self.reference.as_ref().map_or("HEAD", |r| r.as_str());
+        Err(format!("Unable to find '{}' in '{}'", reference, self.repo))?
+    }
+}
+
+// Get the directory a repository is cloned to
+fn cache_dir(repo: &str) -> Result<PathBuf> {
+    let mut path = config::cache_path()?;
+    path.push("git");
+    path.push(dir_name(repo));
+    Ok(path)
+}
+
+// Get a name for the directory of a repository that only contains safe characters
+fn dir_name(repo: &str) -> String {
+    repo.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+// Run a git command in a directory and return its output
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Unable to run git: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!("git {} failed: {}", args[0], stderr.trim()))?;
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+
+////////// TESTS //////////
+
+
+#[test]
+#[allow(non_snake_case)]
+fn parse__with_ref__returns_repo_path_and_ref() {
+    let result = Source::parse("git+https://example.com/themes.git#dark/style.css@v1.2").unwrap();
+
+    assert_eq!(result.repo, "https://example.com/themes.git");
+    assert_eq!(result.path, "dark/style.css");
+    assert_eq!(result.reference, Some(String::from("v1.2")));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn parse__with_ssh_repo_and_no_ref__keeps_at_in_repo() {
+    let result = Source::parse("git+git@example.com:themes.git#style.css").unwrap();
+
+    assert_eq!(result.repo, "git@example.com:themes.git");
+    assert_eq!(result.path, "style.css");
+    assert_eq!(result.reference, None);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn parse__without_path__returns_error() {
+    let result = Source::parse("git+https://example.com/themes.git");
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn parse__with_path_outside_repo__returns_error() {
+    let parent = Source::parse("git+https://example.com/themes.git#../secret.css");
+    let absolute = Source::parse("git+https://example.com/themes.git#/etc/passwd");
+
+    assert!(parent.is_err());
+    assert!(absolute.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn uri__after_parse__returns_original_uri() {
+    let uri = "git+/home/user/themes#style.css@main";
+
+    let result = Source::parse(uri).unwrap().uri();
+
+    assert_eq!(result, uri);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn cache_dir__with_url__replaces_separators() {
+    let result = cache_dir("https://example.com/themes.git").unwrap();
+
+    assert!(result.ends_with("git/https___example.com_themes.git"));
+}
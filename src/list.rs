@@ -1,6 +1,14 @@
-use config::{Config, Style};
+use config::{Config, Style, StyleType};
+use std::collections::{BTreeMap, HashMap};
 use clap::ArgMatches;
+use std::cmp::Reverse;
+use regex::{self, Regex};
+use serde_json;
 use errors::*;
+use atty;
+use toml;
+use target;
+use uri;
 
 pub fn run(matches: &ArgMatches) -> Result<()> {
     // Check if user wants verbose information
@@ -9,53 +17,467 @@ pub fn run(matches: &ArgMatches) -> Result<()> {
     // Load current config file
     let config = Config::load()?;
 
+    // Only keep the styles the user is interested in
+    let filter = Filter::from_matches(matches)?;
+    let mut styles: Vec<Style> = config
+        .styles
+        .iter()
+        .filter(|s| filter.matches(s))
+        .cloned()
+        .collect();
+
     // Sort styles by ID to make it easier on the eyes
-    let mut styles = config.styles.clone();
     styles.sort_by_key(|s| s.id);
+    match matches.value_of("sort") {
+        Some("name") => styles.sort_by_key(|s| s.name.to_lowercase()),
+        Some("type") => styles.sort_by_key(|s| format!("{:?}", s.style_type)),
+        Some("updated") => styles.sort_by_key(|s| Reverse(s.updated)),
+        Some("order") => styles.sort_by_key(|s| (s.path.clone(), s.order)),
+        _ => (),
+    }
+
+    // Find styles which are not installed correctly
+    let mut contents = HashMap::new();
+    let mut problems = HashMap::new();
+    for style in &styles {
+        if !contents.contains_key(&style.path) {
+            contents.insert(style.path.clone(), target::read(&style.path)?);
+        }
+        if let Some(problem) = problem(style, &contents[&style.path]) {
+            problems.insert(style.id, problem);
+        }
+    }
+
+    // Print machine-readable output for all styles at once
+    let entries = || -> Vec<Entry> {
+        styles
+            .iter()
+            .map(|s| Entry {
+                broken: problems.get(&s.id).cloned(),
+                ..Entry::from(s)
+            })
+            .collect()
+    };
+    match matches.value_of("format") {
+        Some("json") => {
+            println!("{}", serde_json::to_string_pretty(&entries())?);
+            return Ok(());
+        }
+        Some("toml") => {
+            print!("{}", toml::to_string(&Entries { styles: entries() })?);
+            return Ok(());
+        }
+        _ => (),
+    }
 
     // Print output for every style
-    for style in styles {
+    let print_style = |style: &Style| {
+        let problem = problems.get(&style.id).cloned();
         if verbose {
-            print_verbose(style);
+            print_verbose(style.clone(), problem);
         } else {
-            print(&style);
+            print(style, problem.is_some());
+        }
+    };
+
+    if !matches.is_present("group") {
+        styles.iter().for_each(print_style);
+        return Ok(());
+    }
+
+    // Styles with multiple tags are part of multiple groups
+    for (i, (tag, styles)) in groups(&styles).iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        match *tag {
+            Some(tag) => println!("@{}", tag),
+            None => println!("Untagged"),
         }
+        styles.iter().cloned().for_each(&print_style);
     }
 
     Ok(())
 }
 
+// Group styles by their tags, styles without tags are the last group
+fn groups(styles: &[Style]) -> Vec<(Option<&str>, Vec<&Style>)> {
+    let mut tagged = BTreeMap::new();
+    for style in styles {
+        for tag in &style.tags {
+            tagged.entry(tag.as_str()).or_insert_with(Vec::new).push(style);
+        }
+    }
+
+    let mut groups: Vec<_> = tagged.into_iter().map(|(tag, s)| (Some(tag), s)).collect();
+    let untagged: Vec<&Style> = styles.iter().filter(|s| s.tags.is_empty()).collect();
+    if !untagged.is_empty() {
+        groups.push((None, untagged));
+    }
+    groups
+}
+
+// Selection of styles based on their properties
+#[derive(Default)]
+pub struct Filter {
+    pub enabled: Option<bool>,
+    pub chrome: Option<bool>,
+    pub style_type: Option<StyleType>,
+    pub domain: Option<String>,
+    pub tag: Option<String>,
+    pub name: Option<Regex>,
+}
+
+impl Filter {
+    // Create a filter from the CLI parameters
+    pub fn from_matches(matches: &ArgMatches) -> Result<Filter> {
+        let mut filter = Filter::default();
+
+        if matches.is_present("enabled") {
+            filter.enabled = Some(true);
+        } else if matches.is_present("disabled") {
+            filter.enabled = Some(false);
+        }
+
+        if matches.is_present("chrome") {
+            filter.chrome = Some(true);
+        } else if matches.is_present("content") {
+            filter.chrome = Some(false);
+        }
+
+        filter.style_type = matches.value_of("type").and_then(uri::parse_type);
+
+        filter.domain = matches.value_of("domain").map(String::from);
+
+        filter.tag = matches.value_of("tag").map(|t| t.replacen("@", "", 1));
+
+        if let Some(name) = matches.value_of("NAME") {
+            let pattern = if matches.is_present("regex") {
+                name.to_owned()
+            } else {
+                glob_to_regex(name)
+            };
+            filter.name = Some(Regex::new(&pattern)?);
+        }
+
+        Ok(filter)
+    }
+
+    // Check if a style passes the filter
+    pub fn matches(&self, style: &Style) -> bool {
+        if self.enabled.is_some() && self.enabled != Some(style.enabled) {
+            return false;
+        }
+
+        let chrome = style.path.ends_with("userChrome.css");
+        if self.chrome.is_some() && self.chrome != Some(chrome) {
+            return false;
+        }
+
+        if self.style_type.is_some() && self.style_type.as_ref() != Some(&style.style_type) {
+            return false;
+        }
+
+        if let Some(ref domain) = self.domain {
+            match style.condition() {
+                Some(ref condition) if condition.contains(domain.as_str()) => (),
+                _ => return false,
+            }
+        }
+
+        if let Some(ref tag) = self.tag {
+            if !style.tags.contains(tag) {
+                return false;
+            }
+        }
+
+        if let Some(ref name) = self.name {
+            if !name.is_match(&style.name) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+// Convert a glob with `*` and `?` wildcards to an anchored regex
+pub fn glob_to_regex(glob: &str) -> String {
+    let mut pattern = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            _ => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    pattern
+}
+
+// All information about a style that is part of the machine-readable output
+#[derive(Serialize)]
+struct Entry<'a> {
+    id: i32,
+    name: &'a str,
+    uri: &'a str,
+    style_type: &'a StyleType,
+    target: &'static str,
+    domain: Option<String>,
+    enabled: bool,
+    #[serde(skip_serializing_if = "no_tags")] tags: &'a [String],
+    updated: Option<u64>,
+    broken: Option<String>,
+    settings: &'a HashMap<String, String>,
+}
+
+impl<'a> From<&'a Style> for Entry<'a> {
+    fn from(style: &'a Style) -> Entry<'a> {
+        Entry {
+            id: style.id,
+            name: &style.name,
+            uri: &style.uri,
+            style_type: &style.style_type,
+            target: target(style),
+            domain: style.condition(),
+            enabled: style.enabled,
+            tags: &style.tags,
+            updated: style.updated,
+            broken: None,
+            settings: &style.settings,
+        }
+    }
+}
+
+// Used for serde to leave out empty tags
+fn no_tags(tags: &&[String]) -> bool {
+    tags.is_empty()
+}
+
+// TOML requires a table at the top level
+#[derive(Serialize)]
+struct Entries<'a> {
+    styles: Vec<Entry<'a>>,
+}
+
+// Shorten the target to the bare minimum
+fn target(style: &Style) -> &'static str {
+    if style.path.to_string_lossy().ends_with("userChrome.css") {
+        "userChrome"
+    } else {
+        "userContent"
+    }
+}
+
+// Get the problem with the block of an enabled style in its target file
+pub fn problem(style: &Style, content: &str) -> Option<String> {
+    if style.enabled {
+        target::health(content, style.id).problem()
+    } else {
+        None
+    }
+}
+
 // Print non-verbose information about a style
-fn print(style: &Style) {
+fn print(style: &Style, broken: bool) {
     // Get the ID as a string, this makes formatting easier
     let id_str = ["(", &style.id.to_string(), ")"].concat();
 
-    // Print the information based on status and target file
-    if !style.enabled {
-        println!("{:5} [DISABLED] {}", id_str, style.name);
+    // Get the tag based on status and target file
+    let (tag, color) = if !style.enabled {
+        ("[DISABLED]", "31")
+    } else if broken {
+        ("[BROKEN]", "33")
     } else if style.path.to_string_lossy().ends_with("userChrome.css") {
-        println!("{:5} [CHROME]   {}", id_str, style.name);
+        ("[CHROME]", "35")
     } else {
-        println!("{:5} [CONTENT]  {}", id_str, style.name);
+        ("[CONTENT]", "32")
     };
-}
 
-// Print verbose information about a style
-fn print_verbose(style: Style) {
-    // Shorten the target to the bare minimum
-    let target = if style.path.to_string_lossy().ends_with("userChrome.css") {
-        "userChrome"
+    // Only color the tag when printing to a terminal
+    let padding = " ".repeat(10 - tag.len());
+    if atty::is(atty::Stream::Stdout) {
+        println!("{:5} \x1b[0;{}m{}\x1b[0m{} {}", id_str, color, tag, padding, style.name);
     } else {
-        "userContent"
-    };
+        println!("{:5} {}{} {}", id_str, tag, padding, style.name);
+    }
+}
 
+// Print verbose information about a style
+pub fn print_verbose(style: Style, problem: Option<String>) {
     // Print the information
     println!("{}", style.name);
     println!("    ID: {}", style.id);
     println!("    URI: {}", style.uri);
-    println!("    TARGET: {}", target);
+    println!("    TARGET: {}", target(&style));
     println!("    TYPE: {:?}", style.style_type);
-    println!("    DOMAIN: {}", style.domain.unwrap_or_default());
+    println!("    DOMAIN: {}", style.condition().unwrap_or_default());
     println!("    ENABLED: {}", style.enabled);
+    if !style.tags.is_empty() {
+        println!("    TAGS: {}", style.tags.join(", "));
+    }
+    if let Some(problem) = problem {
+        println!("    STATUS: Block {}", problem);
+    }
     println!("");
 }
+
+
+////////// TESTS //////////
+
+
+#[cfg(test)]
+use config;
+
+#[test]
+#[allow(non_snake_case)]
+fn glob_to_regex__with_wildcards__returns_anchored_regex() {
+    let result = glob_to_regex("da?k*.css");
+
+    assert_eq!(result, "^da.k.*\\.css$");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn filter_matches__with_name_glob__matches_name() {
+    let mut style = config::dummy_style();
+    style.name = String::from("GitHub Dark");
+    let filter = Filter {
+        name: Some(Regex::new(&glob_to_regex("*Dark")).unwrap()),
+        ..Filter::default()
+    };
+
+    assert!(filter.matches(&style));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn filter_matches__with_disabled_filter_and_enabled_style__returns_false() {
+    let mut style = config::dummy_style();
+    style.enabled = true;
+    let filter = Filter {
+        enabled: Some(false),
+        ..Filter::default()
+    };
+
+    assert!(!filter.matches(&style));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn filter_matches__with_chrome_filter_and_chrome_style__returns_true() {
+    let mut style = config::dummy_style();
+    style.path = "/chrome/userChrome.css".into();
+    let filter = Filter {
+        chrome: Some(true),
+        ..Filter::default()
+    };
+
+    assert!(filter.matches(&style));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn filter_matches__with_domain_substring__returns_true() {
+    let mut style = config::dummy_style();
+    style.domain = Some(String::from("domain(\"github.com\")"));
+    let filter = Filter {
+        domain: Some(String::from("github")),
+        ..Filter::default()
+    };
+
+    assert!(filter.matches(&style));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn filter_matches__with_domain_filter_and_no_domain__returns_false() {
+    let style = config::dummy_style();
+    let filter = Filter {
+        domain: Some(String::from("github")),
+        ..Filter::default()
+    };
+
+    assert!(!filter.matches(&style));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn filter_matches__with_tag_of_style__returns_true() {
+    let mut style = config::dummy_style();
+    style.tags = vec![String::from("work"), String::from("dark")];
+    let filter = Filter {
+        tag: Some(String::from("dark")),
+        ..Filter::default()
+    };
+
+    assert!(filter.matches(&style));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn groups__with_tagged_and_untagged_styles__puts_untagged_last() {
+    let mut style_zero = config::dummy_style();
+    let mut style_one = config::dummy_style();
+    style_zero.tags = vec![String::from("work"), String::from("dark")];
+    style_one.id = 1;
+    let styles = vec![style_zero, style_one];
+
+    let result = groups(&styles);
+
+    let result: Vec<(Option<&str>, Vec<i32>)> = result
+        .into_iter()
+        .map(|(tag, styles)| (tag, styles.iter().map(|s| s.id).collect()))
+        .collect();
+    assert_eq!(result, vec![(Some("dark"), vec![0]), (Some("work"), vec![0]), (None, vec![1])]);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn filter_matches__with_other_type__returns_false() {
+    let style = config::dummy_style();
+    let filter = Filter {
+        style_type: Some(StyleType::Userstyle),
+        ..Filter::default()
+    };
+
+    assert!(!filter.matches(&style));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn entry__with_chrome_style__has_target_userchrome() {
+    let mut style = config::dummy_style();
+    style.path = "/chrome/userChrome.css".into();
+
+    let entry = Entry::from(&style);
+
+    assert_eq!(entry.target, "userChrome");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn entry__with_updated_style__serializes_updated_to_json() {
+    let mut style = config::dummy_style();
+    style.updated = Some(1500000000);
+
+    let json = serde_json::to_string(&Entry::from(&style)).unwrap();
+
+    assert!(json.contains("\"updated\":1500000000"));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn entries__with_style__serializes_to_toml() {
+    let mut style = config::dummy_style();
+    style.domain = Some(String::from("domain(\"kernel.org\")"));
+    let entries = Entries { styles: vec![Entry::from(&style)] };
+
+    let output = toml::to_string(&entries).unwrap();
+
+    let expected = "[[styles]]\nid = 0\nname = \"\"\nuri = \"\"\n\
+                    style_type = \"Local\"\ntarget = \"userContent\"\n\
+                    domain = \"domain(\\\"kernel.org\\\")\"\nenabled = true\n\
+                    \n[styles.settings]\n";
+    assert_eq!(output, expected);
+}
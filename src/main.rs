@@ -9,8 +9,13 @@ extern crate lazy_static;
 #[macro_use]
 extern crate serde_derive;
 
+extern crate atty;
 extern crate base64;
+extern crate notify;
+extern crate rsass;
+extern crate regex;
 extern crate reqwest;
+extern crate serde_json;
 extern crate toml;
 extern crate userstyles;
 
@@ -21,11 +26,33 @@ macro_rules! error {
 }
 
 mod add;
+mod css;
+mod diff;
+mod dir;
+mod domain;
+mod doctor;
+mod dry_run;
+mod edit;
+mod enable;
+mod git;
 mod list;
+mod metadata;
+mod moves;
+mod order;
+mod overrides;
+mod preprocess;
+mod remote;
 mod remove;
+mod rename;
+mod show;
+mod tag;
 mod config;
+mod conflicts;
+mod target;
 mod update;
+mod uri;
 mod userstyle;
+mod watch;
 mod errors {
     error_chain!{
         foreign_links {
@@ -33,6 +60,8 @@ mod errors {
             TomlError(::toml::de::Error);
             ReqwestError(::reqwest::Error);
             TomlSerError(::toml::ser::Error);
+            JsonError(::serde_json::Error);
+            RegexError(::regex::Error);
             ParseIntError(::std::num::ParseIntError);
         }
     }
@@ -65,13 +94,54 @@ fn run() -> Result<()> {
     let yaml = load_yaml!("clap.yml");
     let matches = App::from_yaml(yaml).get_matches();
 
-    // Execute subcommnd with CLI parameters
+    // Don't write anything to disk during a dry run
+    // Global flags are only passed down, so subcommands need to be checked too
+    let mut dry_run = matches.is_present("dry-run");
+    if let (_, Some(submatches)) = matches.subcommand() {
+        dry_run |= submatches.is_present("dry-run");
+    }
+    if dry_run {
+        dry_run::enable();
+    }
+
+    // Remove the temporary files of a dry run, even if the subcommand failed
+    let result = execute(&matches);
+    dry_run::clean();
+    result?;
+
+    // Show what would have been written
+    // Subcommands can use a dry run internally, but only report it if it was requested
+    if dry_run {
+        dry_run::report()?;
+    }
+
+    Ok(())
+}
+
+// Execute subcommnd with CLI parameters
+fn execute(matches: &clap::ArgMatches) -> Result<()> {
     if let Some(subcommand) = matches.subcommand_name() {
         match subcommand {
-            "add" => add::run(submatches(&matches, "add"))?,
-            "list" => list::run(submatches(&matches, "list"))?,
-            "remove" => remove::run(submatches(&matches, "remove"))?,
-            "update" => update::run(submatches(&matches, "update"))?,
+            "add" => add::run(submatches(matches, "add"))?,
+            "conflicts" => conflicts::run()?,
+            "diff" => diff::run(submatches(matches, "diff"))?,
+            "doctor" => doctor::run()?,
+            "disable" => enable::run(submatches(matches, "disable"), false)?,
+            "edit" => edit::run(submatches(matches, "edit"))?,
+            "enable" => enable::run(submatches(matches, "enable"), true)?,
+            "list" => list::run(submatches(matches, "list"))?,
+            "move" => moves::run(submatches(matches, "move"))?,
+            "move-down" => order::shift(submatches(matches, "move-down"), false)?,
+            "move-up" => order::shift(submatches(matches, "move-up"), true)?,
+            "override" => overrides::run(submatches(matches, "override"))?,
+            "remove" => remove::run(submatches(matches, "remove"))?,
+            "reorder" => order::run(submatches(matches, "reorder"))?,
+            "rename" => rename::run(submatches(matches, "rename"))?,
+            "set-domain" => domain::run(submatches(matches, "set-domain"))?,
+            "show" => show::run(submatches(matches, "show"))?,
+            "tag" => tag::run(submatches(matches, "tag"))?,
+            "update" => update::run(submatches(matches, "update"))?,
+            "watch" => watch::run()?,
             _ => (),
         };
     } else {
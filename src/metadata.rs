@@ -0,0 +1,135 @@
+// Information about a style in the comment at the start of the file
+// This supports the UserCSS `==UserStyle==` block and simple front-matter comments
+#[derive(Default, Debug, PartialEq)]
+pub struct Metadata {
+    pub name: Option<String>,
+    pub domain: Option<String>,
+    pub preprocessor: Option<String>,
+    // Default values of the UserCSS `@var` variables
+    pub vars: Vec<(String, String)>,
+}
+
+impl Metadata {
+    pub fn parse(css: &str) -> Metadata {
+        let mut metadata = Metadata::default();
+
+        // Only the first comment of the file is used
+        let css = css.trim();
+        if !css.starts_with("/*") {
+            return metadata;
+        }
+        // The end is searched after the start, since `/*/` does not close the comment
+        let end = css[2..].find("*/").map_or(css.len(), |end| end + 2);
+
+        for line in css[2..end].lines() {
+            let line = line.trim().trim_matches('*').trim();
+            let mut parts = line.splitn(2, char::is_whitespace);
+            let value = parts.nth(1).map(|v| v.trim().to_owned()).filter(|v| !v.is_empty());
+
+            match line.split_whitespace().next() {
+                Some("@name") => metadata.name = value,
+                Some("@domain") => metadata.domain = value,
+                Some("@preprocessor") => metadata.preprocessor = value,
+                Some("@var") => metadata.vars.extend(value.and_then(|v| parse_var(&v))),
+                _ => (),
+            }
+        }
+
+        metadata
+    }
+}
+
+
+// Get the name and default value of a variable like `@var color bg "Background" #fff`
+// Variables with complex defaults like `select` are not supported
+fn parse_var(var: &str) -> Option<(String, String)> {
+    let mut parts = var.splitn(3, char::is_whitespace);
+    let var_type = parts.next()?;
+    let name = parts.next()?;
+    let rest = parts.next()?.trim();
+
+    if !["color", "text", "number", "range", "checkbox"].contains(&var_type) {
+        return None;
+    }
+
+    // The label is either quoted or a single word
+    let value = if rest.starts_with('"') || rest.starts_with('\'') {
+        let quote = &rest[..1];
+        let end = rest[1..].find(quote)? + 2;
+        &rest[end..]
+    } else {
+        &rest[rest.find(char::is_whitespace)?..]
+    };
+
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some((name.to_owned(), value.to_owned()))
+    }
+}
+
+
+////////// TESTS //////////
+
+
+#[test]
+#[allow(non_snake_case)]
+fn metadata_parse__with_userstyle_block__returns_name() {
+    let css = "/* ==UserStyle==\n@name        Dark Kernel\n@version     1.0\n==/UserStyle== */\na {}";
+
+    let result = Metadata::parse(css);
+
+    assert_eq!(result.name, Some(String::from("Dark Kernel")));
+    assert_eq!(result.domain, None);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn metadata_parse__with_front_matter__returns_domain() {
+    let css = "/*\n * @domain domain(\"kernel.org\")\n */\na {}";
+
+    let result = Metadata::parse(css);
+
+    assert_eq!(result.domain, Some(String::from("domain(\"kernel.org\")")));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn metadata_parse__with_comment_hack__returns_name() {
+    let css = "/*/\n@name Hack\n*/\na {}";
+
+    let result = Metadata::parse(css);
+
+    assert_eq!(result.name, Some(String::from("Hack")));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn metadata_parse__with_comment_after_rule__returns_empty() {
+    let css = "a {}\n/* @name Dark */";
+
+    let result = Metadata::parse(css);
+
+    assert_eq!(result, Metadata::default());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn metadata_parse__with_preprocessor_and_vars__returns_both() {
+    let css = "/* ==UserStyle==\n@preprocessor stylus\n@var color bg \"Main background\" #fff\n\
+               @var select theme \"Theme\" {\"dark\": \"1\"}\n==/UserStyle== */";
+
+    let result = Metadata::parse(css);
+
+    assert_eq!(result.preprocessor, Some(String::from("stylus")));
+    assert_eq!(result.vars, vec![(String::from("bg"), String::from("#fff"))]);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn parse_var__with_unquoted_label__returns_value() {
+    let result = parse_var("number size Size 12px");
+
+    assert_eq!(result, Some((String::from("size"), String::from("12px"))));
+}
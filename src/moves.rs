@@ -0,0 +1,121 @@
+use config::{Config, Style};
+use clap::ArgMatches;
+use std::path::Path;
+use std::mem;
+use errors::*;
+use remove;
+use target;
+use add;
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let style = matches.value_of("STYLE").unwrap();
+    let user_chrome = matches.is_present("chrome");
+    println!("Moving '{}'", style);
+
+    // Load config and backup initial state
+    let mut config = Config::load()?;
+    let config_backup = config.clone();
+
+    let id = config.style_id_from_str(style)?;
+    let path = add::target_path(&config, user_chrome);
+    let style = config
+        .styles
+        .iter_mut()
+        .find(|s| s.id == id)
+        .ok_or("Invalid style id or name")?;
+
+    if style.path == path {
+        Err(format!("Style is already in '{}'", path.to_string_lossy()))?;
+    }
+    let old_path = mem::replace(&mut style.path, path);
+
+    let old_condition = style.condition();
+    move_conditions(style, user_chrome);
+
+    let style = style.clone();
+
+    // Both files are restored if the style can't be moved, so it's never installed twice
+    let backups = vec![
+        (old_path.clone(), target::read(&old_path)?),
+        (style.path.clone(), target::read(&style.path)?),
+    ];
+
+    config.write()?;
+
+    // Disabled styles are not part of any target file
+    if style.enabled {
+        let later = config.ids_after(&style);
+        if let Err(e) = move_block(&style, &old_path, old_condition, &later) {
+            error!("Error: {}", e);
+            target::restore(&config_backup, &backups)?;
+            Err("Style has not been moved")?;
+        }
+    }
+
+    println!("Moved '{}' to '{}'", style.name, style.path.to_string_lossy());
+    if !user_chrome && style.condition().is_none() {
+        println!("The style applies to all URLs, use 'set-domain' to restrict it");
+    }
+
+    Ok(())
+}
+
+// Move the installed CSS of a style to its new target file
+fn move_block(
+    style: &Style,
+    old_path: &Path,
+    old_condition: Option<String>,
+    later: &[i32],
+) -> Result<()> {
+    let old_content = target::read(old_path)?;
+    let block = target::find_block(&old_content, style.id)
+        .ok_or("Unable to find style in target file")?;
+    let css = add::replace_domain(block, old_condition.as_ref(), style.condition().as_ref())?;
+
+    // Add the style to the new file first, so it can't get lost
+    let content = target::read(&style.path)?;
+    let content = target::insert_block(&content, style.id, &css, later);
+    target::write(&style.path, &content)?;
+
+    let old_content = remove::remove_style_from_str(&old_content, style.id);
+    target::write(old_path, &old_content)
+}
+
+// Styles in the userChrome.css only apply to the browser UI
+// Their previous conditions are kept, so moving them back restores them
+fn move_conditions(style: &mut Style, user_chrome: bool) {
+    if user_chrome {
+        style.content_matchers = mem::replace(&mut style.matchers, add::chrome_matchers());
+        style.content_domain = style.domain.take();
+    } else {
+        let content_matchers = mem::take(&mut style.content_matchers);
+        let content_domain = style.content_domain.take();
+        if style.matchers == add::chrome_matchers() {
+            style.matchers = content_matchers;
+            style.domain = content_domain;
+        }
+    }
+}
+
+
+////////// TESTS //////////
+
+
+#[cfg(test)]
+use config;
+#[cfg(test)]
+use domain::{Kind, Matcher};
+
+#[test]
+#[allow(non_snake_case)]
+fn move_conditions__to_chrome_and_back__restores_matchers() {
+    let matchers = vec![Matcher::new(Kind::Domain, "kernel.org").unwrap()];
+    let mut style = config::dummy_style();
+    style.matchers = matchers.clone();
+
+    move_conditions(&mut style, true);
+    move_conditions(&mut style, false);
+
+    assert_eq!(style.matchers, matchers);
+    assert!(style.content_matchers.is_empty());
+}
@@ -0,0 +1,152 @@
+use config::{self, Config, Style};
+use clap::ArgMatches;
+use errors::*;
+use target;
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let style = matches.value_of("STYLE").unwrap();
+    let (other, before) = match matches.value_of("before") {
+        Some(other) => (other, true),
+        None => (matches.value_of("after").unwrap(), false),
+    };
+    println!("Reordering '{}'", style);
+
+    let config = Config::load()?;
+    let id = config.style_id_from_str(style)?;
+    let other = config.style_id_from_str(other)?;
+    if id == other {
+        Err("A style can't be moved relative to itself")?;
+    }
+
+    place(config, id, other, before)
+}
+
+// Move a style one position up or down in its target file
+pub fn shift(matches: &ArgMatches, up: bool) -> Result<()> {
+    let style = matches.value_of("STYLE").unwrap();
+    println!("Reordering '{}'", style);
+
+    let config = Config::load()?;
+    let style = config.find_style(style)?.clone();
+
+    match neighbour(&config, &style, up)? {
+        Some(other) => place(config, style.id, other, up),
+        None => {
+            let position = if up { "first" } else { "last" };
+            println!("Style '{}' is already the {} style", style.name, position);
+            Ok(())
+        }
+    }
+}
+
+// Get the id of the style installed right before or after a style
+// Only enabled styles in the same file change the cascade order
+fn neighbour(config: &Config, style: &Style, up: bool) -> Result<Option<i32>> {
+    let ids: Vec<i32> = config
+        .ordered_styles(&style.path)
+        .iter()
+        .filter(|s| s.enabled || s.id == style.id)
+        .map(|s| s.id)
+        .collect();
+    let index = ids.iter()
+        .position(|&id| id == style.id)
+        .ok_or("Invalid style id or name")?;
+
+    let other = if up {
+        index.checked_sub(1)
+    } else {
+        Some(index + 1).filter(|&i| i < ids.len())
+    };
+    Ok(other.map(|other| ids[other]))
+}
+
+// Put a style before or after another style and write the target file in the new order
+fn place(mut config: Config, id: i32, other: i32, before: bool) -> Result<()> {
+    let config_backup = config.clone();
+
+    let (name, path) = {
+        let style = config.find_style(&id.to_string())?;
+        let other = config.find_style(&other.to_string())?;
+        if style.path != other.path {
+            Err(format!("'{}' and '{}' are in different target files", style.name, other.name))?;
+        }
+        (style.name.clone(), style.path.clone())
+    };
+
+    // Get the ids of all styles in the new order
+    let mut styles: Vec<(u32, i32)> = config.styles.iter().map(|s| (s.order, s.id)).collect();
+    styles.sort();
+    let mut ids: Vec<i32> = styles.into_iter().map(|(_, id)| id).filter(|&i| i != id).collect();
+    let index = ids.iter()
+        .position(|&i| i == other)
+        .ok_or("Invalid style id or name")?;
+    ids.insert(if before { index } else { index + 1 }, id);
+
+    // Number all styles again, so there are no gaps or duplicates
+    for style in &mut config.styles {
+        if let Some(index) = ids.iter().position(|&i| i == style.id) {
+            style.order = index as u32 + 1;
+        }
+    }
+
+    config.write()?;
+
+    // Disabled styles are not part of the file, they are sorted when they are enabled
+    let ordered: Vec<i32> = config
+        .ordered_styles(&path)
+        .iter()
+        .map(|s| s.id)
+        .collect();
+    let result = target::read(&path)
+        .and_then(|content| target::write(&path, &target::sort_blocks(&content, &ordered)));
+
+    // Restore config if the target file could not be written
+    if let Err(e) = result {
+        config::restore_config(&config_backup, &e)?;
+    }
+
+    let position = if before { "before" } else { "after" };
+    let other = config.find_style(&other.to_string())?;
+    println!("Moved '{}' {} '{}'", name, position, other.name);
+
+    Ok(())
+}
+
+
+////////// TESTS //////////
+
+
+#[cfg(test)]
+fn ordered_style(id: i32, order: u32, enabled: bool) -> Style {
+    let mut style = config::dummy_style();
+    style.id = id;
+    style.order = order;
+    style.enabled = enabled;
+    style
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn neighbour__with_disabled_style_between__skips_disabled_style() {
+    let styles = vec![
+        ordered_style(0, 1, true),
+        ordered_style(1, 2, false),
+        ordered_style(2, 3, true),
+    ];
+    let config = config::dummy_config(styles.clone());
+
+    let result = neighbour(&config, &styles[2], true).unwrap();
+
+    assert_eq!(result, Some(0));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn neighbour__with_last_enabled_style__returns_none() {
+    let styles = vec![ordered_style(0, 1, true), ordered_style(1, 2, false)];
+    let config = config::dummy_config(styles.clone());
+
+    let result = neighbour(&config, &styles[0], false).unwrap();
+
+    assert_eq!(result, None);
+}
@@ -0,0 +1,161 @@
+use config::{self, Config, Style};
+use std::path::{Path, PathBuf};
+use std::fs::{self, File};
+use clap::ArgMatches;
+use std::io::Read;
+use errors::*;
+use dry_run;
+use target;
+use edit;
+use css;
+use add;
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let style = matches.value_of("STYLE").unwrap();
+    println!("Changing override of '{}'", style);
+
+    let id = Config::load()?.style_id_from_str(style)?;
+    change(id, matches.is_present("clear"), matches.value_of("FILE"))
+}
+
+// Remove the override of a style, replace it with a file or edit it in the $EDITOR
+pub fn change(id: i32, clear: bool, file: Option<&str>) -> Result<()> {
+    // Load config and backup initial state
+    let mut config = Config::load()?;
+    let config_backup = config.clone();
+
+    let style = config
+        .styles
+        .iter_mut()
+        .find(|s| s.id == id)
+        .ok_or("Invalid style id or name")?;
+
+    // Get the new override
+    style.css_override = if clear {
+        None
+    } else if let Some(file) = file {
+        let path = fs::canonicalize(file)?;
+        let mut css = String::new();
+        File::open(&path)?.read_to_string(&mut css)?;
+
+        let errors = css::validate(&css);
+        if !errors.is_empty() {
+            error!("Invalid CSS in '{}':", path.to_string_lossy());
+            for error in errors {
+                error!("    {}", error);
+            }
+            Err("Override has not been changed")?;
+        }
+
+        Some(path)
+    } else {
+        let path = match style.css_override {
+            Some(ref path) => path.clone(),
+            None => managed_path(id)?,
+        };
+
+        edit::edit_file(&path)?;
+        Some(path)
+    };
+
+    let style = style.clone();
+    config.write()?;
+
+    // Disabled styles get their override once they are enabled
+    if style.enabled {
+        if let Err(e) = reinstall(&style) {
+            config::restore_config(&config_backup, &e)?;
+        }
+    }
+
+    println!("Changed override of '{}'", style.name);
+
+    Ok(())
+}
+
+// Get the path of an override that is managed by RUM
+fn managed_path(id: i32) -> Result<PathBuf> {
+    let mut path = config::data_path()?;
+    path.push("overrides");
+    dry_run::create_dir_all(&path)?;
+    path.push(format!("{}.css", id));
+    Ok(path)
+}
+
+// Replace the override in the installed CSS of a style
+fn reinstall(style: &Style) -> Result<()> {
+    let content = target::read(&style.path)?;
+    let block = target::find_block(&content, style.id)
+        .ok_or("Unable to find style in target file")?;
+
+    // Keep the source and only change the override
+    let (css, _) = split(block);
+    let css = match style.css_override {
+        Some(ref path) => append(css, path, style.condition().as_ref())?,
+        None => css.to_owned(),
+    };
+
+    let content = target::replace_block(&content, style.id, &css)
+        .ok_or("Unable to find style in target file")?;
+    target::write(&style.path, &content)
+}
+
+// Add an override after the CSS of a style
+pub fn append(css: &str, path: &Path, domain: Option<&String>) -> Result<String> {
+    // Use the override edited during a dry run
+    let override_css = match dry_run::read(path) {
+        Some(css) => css,
+        None => {
+            let mut content = String::new();
+            File::open(path)
+                .and_then(|mut f| f.read_to_string(&mut content))
+                .map_err(|e| {
+                    format!("Unable to read override '{}': {}", path.to_string_lossy(), e)
+                })?;
+            content
+        }
+    };
+
+    Ok([css, config::RUM_OVERRIDE, &add::with_domain(&override_css, domain)].concat())
+}
+
+// Split the installed CSS of a style into its source and override
+pub fn split(css: &str) -> (&str, Option<&str>) {
+    match css.find(config::RUM_OVERRIDE) {
+        Some(index) => (
+            &css[..index],
+            Some(&css[index + config::RUM_OVERRIDE.len()..]),
+        ),
+        None => (css, None),
+    }
+}
+
+
+////////// TESTS //////////
+
+
+#[test]
+#[allow(non_snake_case)]
+fn split__with_override__returns_source_and_override() {
+    let css = "a {}\n/* RUM OVERRIDE */\nb {}";
+
+    let result = split(css);
+
+    assert_eq!(result, ("a {}", Some("b {}")));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn split__without_override__returns_only_source() {
+    let result = split("a {}");
+
+    assert_eq!(result, ("a {}", None));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn append__with_missing_file__returns_error() {
+    let result = append("a {}", Path::new("/rum/missing/override.css"), None);
+
+    assert!(result.is_err());
+}
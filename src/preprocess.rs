@@ -0,0 +1,325 @@
+use rsass::{self, OutputStyle};
+use css::{self, Token, TokenKind};
+use std::collections::HashSet;
+use metadata::Metadata;
+use git::{self, Source};
+use errors::*;
+
+// Languages that are compiled to CSS before a style is installed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Preprocessor {
+    Scss,
+    Less,
+    Stylus,
+}
+
+impl Preprocessor {
+    // Get the language of a style from its UserCSS metadata or file extension
+    pub fn detect(uri: &str, metadata: &Metadata) -> Option<Preprocessor> {
+        if let Some(ref preprocessor) = metadata.preprocessor {
+            return match preprocessor.as_str() {
+                "scss" | "sass" => Some(Preprocessor::Scss),
+                "less" => Some(Preprocessor::Less),
+                "stylus" => Some(Preprocessor::Stylus),
+                _ => None,
+            };
+        }
+
+        // The file of a git style is part of the URI
+        let path = if uri.starts_with(git::PREFIX) {
+            Source::parse(uri).map(|s| s.path).unwrap_or_default()
+        } else {
+            uri.to_owned()
+        };
+
+        if path.ends_with(".scss") {
+            Some(Preprocessor::Scss)
+        } else if path.ends_with(".less") {
+            Some(Preprocessor::Less)
+        } else if path.ends_with(".styl") {
+            Some(Preprocessor::Stylus)
+        } else {
+            None
+        }
+    }
+}
+
+// Compile the source of a style to CSS
+// Less and Stylus are converted to SCSS first, this only supports their variables
+pub fn compile(uri: &str, source: &str) -> Result<String> {
+    let metadata = Metadata::parse(source);
+    let preprocessor = match Preprocessor::detect(uri, &metadata) {
+        Some(preprocessor) => preprocessor,
+        None => return Ok(source.to_owned()),
+    };
+
+    // Variables of UserCSS styles are defined in the metadata
+    let vars: HashSet<&str> = metadata.vars.iter().map(|v| v.0.as_str()).collect();
+    let mut scss: String = metadata
+        .vars
+        .iter()
+        .map(|var| format!("${}: {};\n", var.0, var.1))
+        .collect();
+
+    match preprocessor {
+        Preprocessor::Scss => scss.push_str(source),
+        Preprocessor::Less => scss.push_str(&less_to_scss(source, vars)),
+        Preprocessor::Stylus => scss.push_str(&stylus_to_scss(source, vars)),
+    }
+
+    // The compiler doesn't parse every document condition, so they are put back afterwards
+    let (mut scss, preludes) = extract_preludes(&scss);
+    // The compiler can't finish an at-rule at the very end of the input
+    scss.push('\n');
+
+    let output = rsass::compile_scss(scss.as_bytes(), OutputStyle::Normal)
+        .map_err(|e| format!("Unable to compile {:?} style: {}", preprocessor, e))?;
+    let mut css = String::from_utf8_lossy(&output).into_owned();
+    for (i, prelude) in preludes.iter().enumerate() {
+        css = css.replace(
+            &format!("{} {} ", DOCUMENT_RULE, prelude_placeholder(i)),
+            &format!("{} {} ", DOCUMENT_RULE, prelude),
+        );
+    }
+    Ok(css)
+}
+
+const DOCUMENT_RULE: &str = "@-moz-document";
+
+fn prelude_placeholder(index: usize) -> String {
+    format!("rum-prelude-{}", index)
+}
+
+// Replace the conditions of `@-moz-document` rules with placeholders
+fn extract_preludes(source: &str) -> (String, Vec<String>) {
+    let tokens = css::tokenize(source);
+    let mut scss = String::with_capacity(source.len());
+    let mut preludes = Vec::new();
+
+    let mut i = 0;
+    while i < tokens.len() {
+        scss.push_str(tokens[i].text);
+        if tokens[i].kind == TokenKind::AtKeyword && tokens[i].text == DOCUMENT_RULE {
+            // The conditions end with the block of the rule
+            let mut prelude = String::new();
+            i += 1;
+            while i < tokens.len() && tokens[i].kind != TokenKind::OpenCurly {
+                prelude.push_str(tokens[i].text);
+                i += 1;
+            }
+            scss.push_str(&format!(" {} ", prelude_placeholder(preludes.len())));
+            preludes.push(prelude.trim().to_owned());
+            continue;
+        }
+        i += 1;
+    }
+
+    (scss, preludes)
+}
+
+// Convert Less variables like `@color: red;` to SCSS
+fn less_to_scss<'a>(source: &'a str, mut vars: HashSet<&'a str>) -> String {
+    let tokens = css::tokenize(source);
+
+    // Find all variable definitions first, so the other at-rules are left alone
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind == TokenKind::AtKeyword && token.text != "@page" {
+            if let Some(next) = next_token(&tokens, i) {
+                if next.kind == TokenKind::Colon {
+                    vars.insert(&token.text[1..]);
+                }
+            }
+        }
+    }
+
+    let mut scss = String::with_capacity(source.len());
+    for token in &tokens {
+        if token.kind == TokenKind::AtKeyword && vars.contains(&token.text[1..]) {
+            scss.push('$');
+            scss.push_str(&token.text[1..]);
+        } else {
+            scss.push_str(token.text);
+        }
+    }
+    scss
+}
+
+// Convert Stylus variables like `color = red` to SCSS
+// Only the CSS-like syntax with braces is supported
+fn stylus_to_scss<'a>(source: &'a str, mut vars: HashSet<&'a str>) -> String {
+    let tokens = css::tokenize(source);
+    let mut scss = String::with_capacity(source.len());
+
+    let mut statement_start = true;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+
+        // Variable names can start with `$`, which is already valid SCSS
+        let name_index = if token.kind == TokenKind::Delim && token.text == "$" {
+            i + 1
+        } else {
+            i
+        };
+
+        // Definitions are `name = value` at the start of a statement
+        let definition = statement_start && tokens.get(name_index).map(|t| t.kind)
+            == Some(TokenKind::Ident)
+            && next_token(&tokens, name_index).map(|t| t.text) == Some("=");
+        if definition {
+            let name = tokens[name_index].text;
+            vars.insert(name);
+
+            // Skip to the value after the `=`
+            i = name_index + 1;
+            while tokens[i].text != "=" {
+                i += 1;
+            }
+            i += 1;
+
+            // The value ends with the line or a semicolon
+            let mut value = String::new();
+            while let Some(token) = tokens.get(i) {
+                match token.kind {
+                    TokenKind::Semicolon => {
+                        i += 1;
+                        break;
+                    }
+                    TokenKind::CloseCurly => break,
+                    TokenKind::Whitespace if token.text.contains('\n') => break,
+                    _ => value.push_str(token.text),
+                }
+                i += 1;
+            }
+
+            scss.push_str(&format!("${}: {};", name, value.trim()));
+            continue;
+        }
+
+        // Property names are followed by a colon and never a variable
+        let property = next_token(&tokens, i).map(|t| t.kind) == Some(TokenKind::Colon);
+        if token.kind == TokenKind::Ident && vars.contains(token.text) && !property {
+            scss.push('$');
+        }
+        scss.push_str(token.text);
+
+        statement_start = match token.kind {
+            TokenKind::Semicolon | TokenKind::OpenCurly | TokenKind::CloseCurly => true,
+            TokenKind::Whitespace if token.text.contains('\n') => true,
+            TokenKind::Whitespace | TokenKind::Comment => statement_start,
+            _ => false,
+        };
+        i += 1;
+    }
+
+    scss
+}
+
+// Get the next token that is not whitespace or a comment
+fn next_token<'a, 'b>(tokens: &'b [Token<'a>], index: usize) -> Option<&'b Token<'a>> {
+    tokens[index + 1..]
+        .iter()
+        .find(|t| t.kind != TokenKind::Whitespace && t.kind != TokenKind::Comment)
+}
+
+
+////////// TESTS //////////
+
+
+#[test]
+#[allow(non_snake_case)]
+fn detect__with_scss_extension__returns_scss() {
+    let result = Preprocessor::detect("/themes/dark.scss", &Metadata::default());
+
+    assert_eq!(result, Some(Preprocessor::Scss));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn detect__with_git_less_file__returns_less() {
+    let result = Preprocessor::detect("git+/themes#dark.less@v1", &Metadata::default());
+
+    assert_eq!(result, Some(Preprocessor::Less));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn detect__with_default_preprocessor_in_metadata__returns_none() {
+    let metadata = Metadata {
+        preprocessor: Some(String::from("default")),
+        ..Metadata::default()
+    };
+
+    let result = Preprocessor::detect("/themes/dark.less", &metadata);
+
+    assert_eq!(result, None);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn less_to_scss__with_variable__replaces_definition_and_use() {
+    let result = less_to_scss("@bg: #000;\n@media print { a { color: @bg; } }", HashSet::new());
+
+    assert_eq!(result, "$bg: #000;\n@media print { a { color: $bg; } }");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn stylus_to_scss__with_variable_without_semicolon__adds_semicolon() {
+    let result = stylus_to_scss("bg = #000\na { color: bg; }", HashSet::new());
+
+    assert_eq!(result, "$bg: #000;\na { color: $bg; }");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn stylus_to_scss__with_property_named_like_variable__keeps_property() {
+    let result = stylus_to_scss("color = red;\na { color: color; }", HashSet::new());
+
+    assert_eq!(result, "$color: red;\na { color: $color; }");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn compile__with_scss_nesting__returns_css() {
+    let result = compile("/themes/dark.scss", "$c: red;\na { b { color: $c; } }").unwrap();
+
+    assert_eq!(result, "a b {\n  color: red;\n}\n");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn compile__with_usercss_var__uses_default_value() {
+    let source = "/* ==UserStyle==\n@preprocessor less\n@var color bg \"BG\" #123456\n\
+                  ==/UserStyle== */\na { color: @bg; }";
+
+    let result = compile("", source).unwrap();
+
+    assert!(result.contains("color: #123456;"));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn compile__with_plain_css__returns_source() {
+    let result = compile("/themes/dark.css", "a { b }").unwrap();
+
+    assert_eq!(result, "a { b }");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn compile__with_usercss_style__keeps_document_conditions() {
+    let source = "/* ==UserStyle==\n@name Dark\n@preprocessor stylus\n\
+                  @var color bg \"BG\" #123456\n==/UserStyle== */\n\
+                  @-moz-document domain(\"a.org\"), url-prefix(\"https://b.org/\") {\n\
+                  a { b { color: bg; } }\n}\n\
+                  @-moz-document domain(\"c.org\") {\n  c { color: red; }\n}";
+
+    let result = compile("", source).unwrap();
+
+    assert!(result.contains(
+        "@-moz-document domain(\"a.org\"), url-prefix(\"https://b.org/\") {\n  a b {\n    \
+         color: #123456;"
+    ));
+    assert!(result.contains("@-moz-document domain(\"c.org\") {\n  c {\n    color: red;"));
+}
@@ -0,0 +1,205 @@
+use reqwest::header::{ETag, Headers, LastModified};
+use reqwest::{self, Response, StatusCode};
+use std::collections::HashMap;
+use config::HttpMetadata;
+use std::sync::Mutex;
+use std::io::Read;
+use errors::*;
+
+// Styles that have already been downloaded by a conditional request
+lazy_static! {
+    static ref DOWNLOADS: Mutex<HashMap<String, (String, HttpMetadata)>> = {
+        Mutex::new(HashMap::new())
+    };
+}
+
+// Download a remote style and the information required to update it
+pub fn download(url: &str) -> Result<(String, HttpMetadata)> {
+    // Don't download a style again if the conditional request already returned it
+    let download = DOWNLOADS
+        .lock()
+        .map_err(|_| "Unable to lift download lock")?
+        .remove(url);
+    if let Some(download) = download {
+        return Ok(download);
+    }
+
+    let mut response = reqwest::get(url)?;
+    if !response.status().is_success() {
+        Err(format!("Unable to download '{}': {}", url, response.status()))?;
+    }
+
+    let http = metadata(&response);
+
+    let mut css = String::new();
+    response.read_to_string(&mut css)?;
+
+    Ok((css, http))
+}
+
+// Check if a remote style has changed since the last download
+// Only the headers are requested when the server supports conditional requests
+// A changed style is kept for the next download, so it is not requested twice
+pub fn modified(url: &str, http: &HttpMetadata) -> Result<bool> {
+    // The server can't tell if the style changed without the headers of the last download
+    if http.etag.is_none() && http.last_modified.is_none() {
+        return Ok(true);
+    }
+
+    let mut headers = Headers::new();
+    if let Some(ref etag) = http.etag {
+        headers.set_raw("If-None-Match", etag.clone());
+    }
+    if let Some(ref last_modified) = http.last_modified {
+        headers.set_raw("If-Modified-Since", last_modified.clone());
+    }
+
+    let mut response = reqwest::Client::new()?.get(url)?.headers(headers).send()?;
+    if response.status() == StatusCode::NotModified {
+        return Ok(false);
+    }
+    if !response.status().is_success() {
+        Err(format!("Unable to download '{}': {}", url, response.status()))?;
+    }
+
+    let http = metadata(&response);
+    let mut css = String::new();
+    response.read_to_string(&mut css)?;
+
+    DOWNLOADS
+        .lock()
+        .map_err(|_| "Unable to lift download lock")?
+        .insert(url.to_owned(), (css, http));
+
+    Ok(true)
+}
+
+// Get the information about a download that is stored in the config
+fn metadata(response: &Response) -> HttpMetadata {
+    HttpMetadata {
+        url: response.url().to_string(),
+        etag: response.headers().get::<ETag>().map(|h| h.to_string()),
+        last_modified: response.headers().get::<LastModified>().map(|h| h.to_string()),
+    }
+}
+
+
+////////// TESTS //////////
+
+
+#[cfg(test)]
+use std::net::TcpListener;
+#[cfg(test)]
+use std::thread::{self, JoinHandle};
+#[cfg(test)]
+use std::io::Write;
+
+// Answer a single request with a fixed response and return the request
+#[cfg(test)]
+fn serve(response: &'static str) -> (String, JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/style.css", listener.local_addr().unwrap());
+
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        while !request.ends_with(b"\r\n\r\n") {
+            let read = stream.read(&mut buffer).unwrap();
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buffer[..read]);
+        }
+
+        stream.write_all(response.as_bytes()).unwrap();
+        String::from_utf8_lossy(&request).to_lowercase()
+    });
+
+    (url, handle)
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn download__with_etag__returns_css_and_etag() {
+    let (url, server) = serve(
+        "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 5\r\n\r\na { }",
+    );
+
+    let (css, http) = download(&url).unwrap();
+    server.join().unwrap();
+
+    assert_eq!(css, "a { }");
+    assert_eq!(http.url, url);
+    assert_eq!(http.etag, Some(String::from("\"v1\"")));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn download__with_not_found__returns_error() {
+    let (url, server) = serve("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+
+    let result = download(&url);
+    server.join().unwrap();
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn modified__with_not_modified__returns_false() {
+    let (url, server) = serve("HTTP/1.1 304 Not Modified\r\n\r\n");
+    let http = HttpMetadata {
+        etag: Some(String::from("\"v1\"")),
+        last_modified: Some(String::from("Sun, 01 Oct 2017 10:00:00 GMT")),
+        ..HttpMetadata::default()
+    };
+
+    let result = modified(&url, &http).unwrap();
+    let request = server.join().unwrap();
+
+    assert!(!result);
+    assert!(request.contains("if-none-match: \"v1\"\r\n"));
+    assert!(request.contains("if-modified-since: sun, 01 oct 2017 10:00:00 gmt\r\n"));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn modified__with_new_version__returns_true() {
+    let (url, server) = serve("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\na { }");
+    let http = HttpMetadata {
+        etag: Some(String::from("\"v1\"")),
+        ..HttpMetadata::default()
+    };
+
+    let result = modified(&url, &http).unwrap();
+    server.join().unwrap();
+
+    assert!(result);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn download__after_modified__returns_css_without_request() {
+    let (url, server) = serve("HTTP/1.1 200 OK\r\nETag: \"v2\"\r\nContent-Length: 5\r\n\r\nb { }");
+    let http = HttpMetadata {
+        etag: Some(String::from("\"v1\"")),
+        ..HttpMetadata::default()
+    };
+
+    assert!(modified(&url, &http).unwrap());
+    server.join().unwrap();
+    let (css, http) = download(&url).unwrap();
+
+    assert_eq!(css, "b { }");
+    assert_eq!(http.etag, Some(String::from("\"v2\"")));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn modified__without_headers__returns_true_without_request() {
+    let result = modified("http://127.0.0.1:0/style.css", &HttpMetadata::default()).unwrap();
+
+    assert!(result);
+}
@@ -1,12 +1,13 @@
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::Path;
 use clap::ArgMatches;
-use std::fs::File;
 use errors::*;
+use dry_run;
+use target;
 use config;
 
 pub fn run(matches: &ArgMatches) -> Result<()> {
     let styles = matches.values_of_lossy("STYLE").unwrap();
+    let styles = config::Config::load()?.expand_tags(styles)?;
 
     for style in styles {
         println!("");
@@ -24,7 +25,7 @@ pub fn remove_style(style: &str) -> Result<()> {
     let config_backup = config.clone();
 
     // Remove style from config
-    let removed_style = config.remove_style(style).ok_or("Invalid style id or name")?;
+    let removed_style = config.remove_style(style)?;
 
     // Save config
     config.write()?;
@@ -42,33 +43,27 @@ pub fn remove_style(style: &str) -> Result<()> {
     Ok(())
 }
 
-fn remove_from_file(id: i32, path: &PathBuf) -> Result<()> {
-    // Read current content
-    let mut content = String::new();
-    {
-        match File::open(path) {
-            Ok(mut file) => {
-                file.read_to_string(&mut content)?;
-            }
-            Err(e) => {
-                error!("Unable to find '{}': {}", path.to_string_lossy(), e);
-                println!("Removing style only from config");
-                return Ok(());
-            }
-        };
+fn remove_from_file(id: i32, path: &Path) -> Result<()> {
+    if !path.exists() && dry_run::read(path).is_none() {
+        error!("Unable to find '{}'", path.to_string_lossy());
+        println!("Removing style only from config");
+        return Ok(());
     }
 
+    // Read current content
+    let content = target::read(path)?;
+
     // Get new content
-    content = remove_style_from_str(&content, id);
+    let content = remove_style_from_str(&content, id);
 
     // Write new file
-    File::create(path)?.write_all(content.as_bytes())?;
+    target::write(path, &content)?;
 
     Ok(())
 }
 
 // Remove a style with RUM tags from a string slice
-fn remove_style_from_str(user_content: &str, id: i32) -> String {
+pub fn remove_style_from_str(user_content: &str, id: i32) -> String {
     // Replace placeholders with ID
     let start_str = config::RUM_START.replace("{}", &id.to_string());
     let end_str = config::RUM_END.replace("{}", &id.to_string());
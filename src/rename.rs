@@ -0,0 +1,30 @@
+use clap::ArgMatches;
+use config::Config;
+use errors::*;
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let style = matches.value_of("STYLE").unwrap();
+    let name = matches.value_of("NAME").unwrap().trim();
+    println!("Renaming '{}'", style);
+
+    let mut config = Config::load()?;
+    let id = config.style_id_from_str(style)?;
+
+    // Names are used to find styles, so they have to be unique
+    config.check_name(name, Some(id))?;
+
+    let style = config
+        .styles
+        .iter_mut()
+        .find(|s| s.id == id)
+        .ok_or("Invalid style id or name")?;
+    let old_name = style.name.clone();
+    style.name = name.to_owned();
+
+    // The target file only contains the id, so only the config has to change
+    config.write()?;
+
+    println!("Renamed '{}' to '{}'", old_name, name);
+
+    Ok(())
+}
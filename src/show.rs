@@ -0,0 +1,84 @@
+use config::{Config, StyleType};
+use std::collections::HashMap;
+use clap::ArgMatches;
+use overrides;
+use userstyle;
+use errors::*;
+use target;
+use list;
+use css;
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let style = matches.value_of("STYLE").unwrap();
+
+    // Get the style from the config
+    let config = Config::load()?;
+    let style = config.find_style(style)?.clone();
+
+    // Get the CSS before the style is consumed
+    let content = target::read(&style.path)?;
+    let css = target::find_block(&content, style.id);
+    let (enabled, settings) = (style.enabled, style.settings.clone());
+    let override_path = style.css_override.clone();
+
+    // Only userstyles.org provides labels for its settings
+    let labels = if style.style_type == StyleType::Userstyle && !settings.is_empty() {
+        userstyle::setting_labels(&style.uri).unwrap_or_else(|e| {
+            error!("Unable to get setting labels: {}", e);
+            HashMap::new()
+        })
+    } else {
+        HashMap::new()
+    };
+
+    let problem = list::problem(&style, &content);
+    list::print_verbose(style, problem);
+
+    // Print settings sorted by their key
+    if !settings.is_empty() {
+        println!("Settings:");
+        let mut keys: Vec<&String> = settings.keys().collect();
+        keys.sort();
+        for key in keys {
+            match labels.get(key) {
+                Some(label) => println!("    {} ({}): {}", label, key, settings[key]),
+                None => println!("    {}: {}", key, settings[key]),
+            }
+        }
+        println!();
+    }
+
+    // Print the installed CSS
+    let css = match css {
+        Some(css) => css,
+        None if !enabled => {
+            println!("Style is disabled and not installed");
+            return Ok(());
+        }
+        None => Err("Unable to find style in target file")?,
+    };
+
+    let highlight = matches.is_present("highlight");
+    let (source, css_override) = overrides::split(css);
+    print_css(source, highlight);
+
+    // Print the user's override separately
+    if let Some(css_override) = css_override {
+        println!();
+        match override_path {
+            Some(path) => println!("Override ({}):", path.to_string_lossy()),
+            None => println!("Override:"),
+        }
+        print_css(css_override, highlight);
+    }
+
+    Ok(())
+}
+
+fn print_css(css: &str, highlight: bool) {
+    if highlight {
+        println!("{}", css::highlight(css));
+    } else {
+        println!("{}", css);
+    }
+}
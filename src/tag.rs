@@ -0,0 +1,72 @@
+use clap::ArgMatches;
+use config::Config;
+use errors::*;
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let (add, matches) = match matches.subcommand() {
+        ("add", Some(matches)) => (true, matches),
+        ("remove", Some(matches)) => (false, matches),
+        _ => Err("No tag operation specified (use -h for help)")?,
+    };
+
+    let mut config = Config::load()?;
+    let styles = matches.values_of_lossy("STYLE").unwrap();
+    let tags = matches
+        .values_of("TAG")
+        .unwrap()
+        .map(parse_tag)
+        .collect::<Result<Vec<String>>>()?;
+
+    // Tags can be used to select the styles that should be tagged too
+    let mut ids = Vec::new();
+    for style in config.expand_tags(styles)? {
+        ids.push(config.style_id_from_str(&style)?);
+    }
+
+    for style in config.styles.iter_mut().filter(|s| ids.contains(&s.id)) {
+        for tag in &tags {
+            if add && !style.tags.contains(tag) {
+                style.tags.push(tag.clone());
+            } else if !add {
+                style.tags.retain(|t| t != tag);
+            }
+        }
+        style.tags.sort();
+
+        println!("Tags of '{}': {}", style.name, style.tags.join(", "));
+    }
+
+    // Tags are not part of the target file
+    config.write()
+}
+
+// Get a tag without the leading `@` and make sure it can be used for selecting styles
+fn parse_tag(tag: &str) -> Result<String> {
+    let tag = tag.replacen("@", "", 1);
+
+    if tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || c == ',' || c == '@') {
+        Err(format!("Invalid tag '{}', tags can't contain spaces, ',' or '@'", tag))?;
+    }
+
+    Ok(tag)
+}
+
+
+////////// TESTS //////////
+
+
+#[test]
+#[allow(non_snake_case)]
+fn parse_tag__with_leading_at__removes_at() {
+    let result = parse_tag("@dark").unwrap();
+
+    assert_eq!(result, "dark");
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn parse_tag__with_space__returns_error() {
+    let result = parse_tag("dark mode");
+
+    assert!(result.is_err());
+}
@@ -0,0 +1,419 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::fs::{self, File};
+use errors::*;
+use dry_run;
+use config;
+use css;
+
+// State of the block of a style in its target file
+#[derive(Debug, PartialEq)]
+pub enum Health {
+    Ok,
+    Missing,
+    Invalid(String),
+    Repaired(String),
+    Quarantined(String),
+}
+
+impl Health {
+    // Describe what is wrong with the block
+    pub fn problem(&self) -> Option<String> {
+        match *self {
+            Health::Ok => None,
+            Health::Missing => Some(String::from("is missing from the target file")),
+            Health::Invalid(ref reason) => Some(format!("has invalid CSS ({})", reason)),
+            Health::Repaired(ref reason) => Some(format!("has been repaired ({})", reason)),
+            Health::Quarantined(ref reason) => Some(format!("has been quarantined ({})", reason)),
+        }
+    }
+}
+
+// Read the content of a target file
+// Returns an empty string if the file does not exist yet
+pub fn read(path: &Path) -> Result<String> {
+    // Use the changes of previous operations during a dry run
+    if let Some(content) = dry_run::read(path) {
+        return Ok(content);
+    }
+
+    let mut content = String::new();
+    if path.exists() {
+        File::open(path)?.read_to_string(&mut content)?;
+    }
+    Ok(content)
+}
+
+// Replace the content of a target file
+pub fn write(path: &Path, content: &str) -> Result<()> {
+    // Make sure a broken style can't break any other style
+    let content = isolate(content)?;
+
+    if dry_run::enabled() {
+        return dry_run::write(path, &content);
+    }
+
+    File::create(path)?.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+// Restore the config and the content of target files after a change failed
+// Every file is restored even if another one fails
+pub fn restore(config_backup: &config::Config, backups: &[(PathBuf, String)]) -> Result<()> {
+    println!("Attempting to restore config and target files");
+
+    let mut failed = Vec::new();
+    if config_backup.write().is_err() {
+        failed.push(String::from("config"));
+    }
+    for (path, content) in backups {
+        // Files that have not been written yet don't need to be restored
+        if read(path).ok().as_ref() == Some(content) {
+            continue;
+        }
+
+        if write(path, content).is_err() {
+            failed.push(format!("'{}'", path.to_string_lossy()));
+        }
+    }
+
+    if !failed.is_empty() {
+        Err(format!(
+            "Unable to recover {}\nPlease ensure these files are not corrupted",
+            failed.join(", ")
+        ))?;
+    }
+
+    println!("Successfully restored config and target files");
+    Ok(())
+}
+
+// Get the ids of all styles in a target file
+pub fn block_ids(content: &str) -> Vec<i32> {
+    let start = config::RUM_START.trim().replace("{} */", "");
+
+    content
+        .match_indices(&start)
+        .filter_map(|(index, _)| {
+            let rest = &content[index + start.len()..];
+            let end = rest.find(" */")?;
+            rest[..end].trim().parse().ok()
+        })
+        .collect()
+}
+
+// Check if the block of a style is working
+pub fn health(content: &str, id: i32) -> Health {
+    let block = match find_block(content, id) {
+        Some(block) => block,
+        None => return Health::Missing,
+    };
+
+    if let Some(reason) = marker_reason(block, config::RUM_QUARANTINED) {
+        return Health::Quarantined(reason);
+    }
+    if let Some(reason) = marker_reason(block, config::RUM_REPAIRED) {
+        return Health::Repaired(reason);
+    }
+
+    match css::validate(block).first() {
+        Some(error) => Health::Invalid(error.to_string()),
+        None => Health::Ok,
+    }
+}
+
+// Repair every broken block, or move it out of the file if it can't be repaired
+// Otherwise an unclosed bracket could swallow all styles after it
+fn isolate(content: &str) -> Result<String> {
+    let mut content = content.to_owned();
+
+    for id in block_ids(&content) {
+        let block = match find_block(&content, id) {
+            Some(block) => block.to_owned(),
+            None => continue,
+        };
+
+        // The reason is part of a comment, so it can't contain the end of a comment
+        let reason = match css::validate(&block).first() {
+            Some(error) => error.to_string().replace("*/", "* /"),
+            None => continue,
+        };
+
+        let replacement = match css::repair(&block) {
+            Some(repaired) => {
+                error!("Repaired broken style {}: {}", id, reason);
+                [&config::RUM_REPAIRED.replace("{}", &reason), repaired.as_str()].concat()
+            }
+            None => {
+                let path = quarantine(id, &block)?;
+                let moved = if dry_run::enabled() { "would be" } else { "has been" };
+                error!(
+                    "Quarantined broken style {}: {}\nIts CSS {} moved to '{}'",
+                    id,
+                    reason,
+                    moved,
+                    path.to_string_lossy()
+                );
+                config::RUM_QUARANTINED.replace("{}", &reason)
+            }
+        };
+
+        if let Some(replaced) = replace_block(&content, id, &replacement) {
+            content = replaced;
+        }
+    }
+
+    Ok(content)
+}
+
+// Save the CSS of a broken style outside of the target file
+fn quarantine(id: i32, css: &str) -> Result<PathBuf> {
+    let mut path = config::cache_path()?;
+    path.push("quarantine");
+    path.push(format!("{}.css", id));
+
+    if !dry_run::enabled() {
+        fs::create_dir_all(path.parent().ok_or("Invalid cache path")?)?;
+        File::create(&path)?.write_all(css.as_bytes())?;
+    }
+
+    Ok(path)
+}
+
+// Get the reason from a marker at the start of a block
+fn marker_reason(block: &str, marker: &str) -> Option<String> {
+    let mut parts = marker.split("{}");
+    let (prefix, suffix) = (parts.next()?, parts.next()?.trim_matches('\n'));
+
+    if !block.starts_with(prefix) {
+        return None;
+    }
+    let end = block.find(suffix)?;
+    Some(block[prefix.len()..end].to_owned())
+}
+
+// Get the CSS between the RUM tags of a style
+pub fn find_block(content: &str, id: i32) -> Option<&str> {
+    // Replace placeholders with ID
+    let start_str = config::RUM_START.replace("{}", &id.to_string());
+    let end_str = config::RUM_END.replace("{}", &id.to_string());
+
+    // Get index of start and end tags
+    let start = content.find(&start_str)? + start_str.len();
+    let end = content.find(&end_str)?;
+
+    if start <= end {
+        Some(&content[start..end])
+    } else {
+        None
+    }
+}
+
+// Add the CSS of a style before the first block of the styles installed after it
+pub fn insert_block(content: &str, id: i32, css: &str, later: &[i32]) -> String {
+    let start = config::RUM_START.replace("{}", &id.to_string());
+    let end = config::RUM_END.replace("{}", &id.to_string());
+    let block = [&start, css, &end].concat();
+
+    let index = later
+        .iter()
+        .filter_map(|id| content.find(&config::RUM_START.replace("{}", &id.to_string())))
+        .min();
+    match index {
+        Some(index) => [&content[..index], &block, &content[index..]].concat(),
+        None => [content, &block].concat(),
+    }
+}
+
+// Put the blocks of styles in the order of `ids`
+// The blocks swap places, so everything else in the file stays where it is
+pub fn sort_blocks(content: &str, ids: &[i32]) -> String {
+    // Get the position of every block including its tags, in the order of the file
+    let mut blocks: Vec<(usize, usize, i32)> = Vec::new();
+    for id in block_ids(content).into_iter().filter(|id| ids.contains(id)) {
+        let start_str = config::RUM_START.replace("{}", &id.to_string());
+        let end_str = config::RUM_END.replace("{}", &id.to_string());
+        let start = match content.find(&start_str) {
+            Some(start) => start,
+            None => continue,
+        };
+        let end = match content[start..].find(&end_str) {
+            Some(end) => start + end + end_str.len(),
+            None => continue,
+        };
+
+        // Blocks can only be moved if they don't overlap
+        if let Some(&(_, last_end, _)) = blocks.last() {
+            if start < last_end {
+                continue;
+            }
+        }
+        blocks.push((start, end, id));
+    }
+
+    let sorted = ids.iter().filter_map(|&id| blocks.iter().find(|b| b.2 == id));
+
+    let mut result = String::with_capacity(content.len());
+    let mut last_end = 0;
+    for (&(start, end, _), &(sorted_start, sorted_end, _)) in blocks.iter().zip(sorted) {
+        result.push_str(&content[last_end..start]);
+        result.push_str(&content[sorted_start..sorted_end]);
+        last_end = end;
+    }
+    result.push_str(&content[last_end..]);
+    result
+}
+
+// Replace the CSS between the RUM tags of a style
+pub fn replace_block(content: &str, id: i32, css: &str) -> Option<String> {
+    let block = find_block(content, id)?;
+
+    // Get the position of the block inside the content
+    let start = block.as_ptr() as usize - content.as_ptr() as usize;
+    let end = start + block.len();
+
+    Some([&content[..start], css, &content[end..]].concat())
+}
+
+
+////////// TESTS //////////
+
+
+#[test]
+#[allow(non_snake_case)]
+fn find_block__with_id_one__returns_css_of_style_one() {
+    let content = "foobar\n\n/* RUM START 0 */\nzero\n/* RUM END 0 */\n\
+                   \n/* RUM START 1 */\none\n/* RUM END 1 */\n";
+
+    let result = find_block(content, 1);
+
+    assert_eq!(result, Some("one"));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn find_block__with_end_before_start__returns_none() {
+    let content = "\n/* RUM END 0 */\nzero\n/* RUM START 0 */\n";
+
+    let result = find_block(content, 0);
+
+    assert_eq!(result, None);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn find_block__with_tags_missing__returns_none() {
+    let result = find_block("no tags", 0);
+
+    assert_eq!(result, None);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn replace_block__with_id_zero__replaces_only_style_zero() {
+    let content = "\n/* RUM START 0 */\nzero\n/* RUM END 0 */\n\
+                   \n/* RUM START 1 */\none\n/* RUM END 1 */\n";
+
+    let result = replace_block(content, 0, "new").unwrap();
+
+    assert_eq!(
+        result,
+        "\n/* RUM START 0 */\nnew\n/* RUM END 0 */\n\
+         \n/* RUM START 1 */\none\n/* RUM END 1 */\n"
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn replace_block__with_tags_missing__returns_none() {
+    let result = replace_block("no tags", 0, "new");
+
+    assert_eq!(result, None);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn block_ids__with_two_blocks__returns_both_ids() {
+    let content = "\n/* RUM START 0 */\nzero\n/* RUM END 0 */\n\
+                   \n/* RUM START 12 */\ntwelve\n/* RUM END 12 */\n";
+
+    let result = block_ids(content);
+
+    assert_eq!(result, vec![0, 12]);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn isolate__with_unclosed_brace__repairs_only_broken_block() {
+    let content = "\n/* RUM START 0 */\na {\n/* RUM END 0 */\n\
+                   \n/* RUM START 1 */\nb {}\n/* RUM END 1 */\n";
+
+    let result = isolate(content).unwrap();
+
+    assert_eq!(
+        result,
+        "\n/* RUM START 0 */\n/* RUM REPAIRED 1:3: Unclosed '{' */\na {}\n/* RUM END 0 */\n\
+         \n/* RUM START 1 */\nb {}\n/* RUM END 1 */\n"
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn health__with_repaired_block__returns_reason() {
+    let content = "\n/* RUM START 0 */\n/* RUM REPAIRED 1:3: Unclosed '{' */\na {}\n/* RUM END 0 */\n";
+
+    let result = health(content, 0);
+
+    assert_eq!(result, Health::Repaired(String::from("1:3: Unclosed '{'")));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn health__with_quarantined_block__returns_reason() {
+    let content = "\n/* RUM START 0 */\n/* RUM QUARANTINED 1:1: Unexpected '}' */\n/* RUM END 0 */\n";
+
+    let result = health(content, 0);
+
+    assert_eq!(result, Health::Quarantined(String::from("1:1: Unexpected '}'")));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn health__with_invalid_block__returns_invalid() {
+    let content = "\n/* RUM START 0 */\na { \"b }\n/* RUM END 0 */\n";
+
+    let result = health(content, 0);
+
+    assert_eq!(result, Health::Invalid(String::from("1:5: Unclosed string")));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn insert_block__with_later_style__inserts_before_later_style() {
+    let content = "foo\n\n/* RUM START 0 */\nzero\n/* RUM END 0 */\n\
+                   \n/* RUM START 1 */\none\n/* RUM END 1 */\n";
+
+    let result = insert_block(content, 2, "two", &[3, 1]);
+
+    assert_eq!(
+        result,
+        "foo\n\n/* RUM START 0 */\nzero\n/* RUM END 0 */\n\
+         \n/* RUM START 2 */\ntwo\n/* RUM END 2 */\n\
+         \n/* RUM START 1 */\none\n/* RUM END 1 */\n"
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn sort_blocks__with_reversed_ids__swaps_blocks_around_other_content() {
+    let content = "\n/* RUM START 0 */\nzero\n/* RUM END 0 */\n\
+                   foo\n/* RUM START 1 */\none\n/* RUM END 1 */\nbar\n";
+
+    let result = sort_blocks(content, &[1, 0]);
+
+    assert_eq!(
+        result,
+        "\n/* RUM START 1 */\none\n/* RUM END 1 */\n\
+         foo\n/* RUM START 0 */\nzero\n/* RUM END 0 */\nbar\n"
+    );
+}
@@ -1,76 +1,68 @@
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::Path;
 use clap::ArgMatches;
 use config::Config;
 use errors::*;
-use std::fs;
+use dry_run;
+use enable;
+use remote;
 use remove;
-use config;
+use target;
 use add;
 
 pub fn run(matches: &ArgMatches) -> Result<()> {
     // Make sure the /chrome folder exists
     let config = Config::load()?;
-    fs::create_dir_all(config.chrome_path)?;
+    dry_run::create_dir_all(&config.chrome_path)?;
 
     let styles = match matches.values_of_lossy("STYLE") {
-        Some(styles) => styles,
+        Some(styles) => Config::load()?.expand_tags(styles)?,
         None => Config::load()?
             .styles
             .iter()
-            .map(|s| s.name.clone())
+            .map(|s| s.id.to_string())
             .collect(),
     };
     let edit = matches.is_present("edit");
+    let force = matches.is_present("force");
+
+    // Toggle all styles at once, so every target file is only written once
+    if matches.is_present("toggle") {
+        let config = Config::load()?;
+        let mut changes = Vec::new();
+        for style in &styles {
+            let style = config.find_style(style)?;
+            changes.push((style.id, !style.enabled));
+        }
+        return enable::set_enabled(&changes, force);
+    }
 
     for style in styles {
-        if matches.is_present("toggle") {
-            println!("");
-            toggle_style(&style)?;
+        println!("");
+        if !edit && !modified(&style)? {
+            println!("Style '{}' is already up to date", style);
         } else {
-            println!("");
-            update_style(&style, edit)?;
+            update_style(&style, edit, force)?;
         }
     }
 
     Ok(())
 }
 
-// Toggles a style
-// Enable if Disabled, Disable if Enabled
-fn toggle_style(style: &str) -> Result<()> {
-    println!("Toggling '{}'", style);
-
-    // Load config and backup initial state
-    let mut config = Config::load()?;
-    let config_backup = config.clone();
-
-    // Get the id of the style that will be toggled
-    let id = config
-        .style_id_from_str(style)
-        .ok_or("Invalid style id or name")?;
-
-    // Toggle the style in the config
-    config.toggle_style(id)?;
-
-    config.write()?;
-
-    // Update target file
-    let result = update_style(style, false);
+// Check if a style has to be downloaded again
+// Only remote styles can tell if they have not changed
+fn modified(style: &str) -> Result<bool> {
+    let config = Config::load()?;
+    let style = config.find_style(style)?;
 
-    // Recover config if update failed
-    if let Err(error) = result {
-        config::restore_config(&config_backup, &error)?;
+    match style.http {
+        Some(ref http) => remote::modified(&style.uri, http),
+        None => Ok(true),
     }
-
-    println!("Toggled style '{}'", style);
-
-    Ok(())
 }
 
 // Update a style
 // Asks about settings again if `edit` is true
-fn update_style(style: &str, edit: bool) -> Result<()> {
+fn update_style(style: &str, edit: bool, force: bool) -> Result<()> {
     println!("Updating '{}'", style);
 
     // Load config and backup initial state
@@ -78,18 +70,14 @@ fn update_style(style: &str, edit: bool) -> Result<()> {
     let config_backup = config.clone();
 
     // Get current style
-    let current_style = config
-        .remove_style(style)
-        .ok_or("Unable to find style in config")?;
+    let mut current_style = config.remove_style(style)?;
 
     // Load initial state of the target file as backup
     let target_path = current_style.path.clone();
-    let mut file_backup = String::new();
-    {
-        // If file could not be found, leave the file backup empty
-        // This will just create an empty file after recovery
-        let _ = fs::File::open(&target_path).and_then(|mut f| f.read_to_string(&mut file_backup));
-    }
+
+    // If file could not be found, leave the file backup empty
+    // This will just create an empty file after recovery
+    let file_backup = target::read(&target_path).unwrap_or_default();
 
     // Get the chrome path and check target file
     let path_str = target_path.to_str().ok_or("Invalid file path")?;
@@ -98,21 +86,18 @@ fn update_style(style: &str, edit: bool) -> Result<()> {
     // Remove old style
     remove::remove_style(&current_style.id.to_string())?;
 
-    let enabled = current_style.enabled;
+    // Ask about all settings again, everything else is kept
+    if edit {
+        current_style.settings.clear();
+    }
+
     // Add new updated style
-    let result = if edit {
-        add::add_style(&current_style.uri, user_chrome, None, !enabled)
-    } else {
-        add::add_style(
-            &current_style.uri.clone(),
-            user_chrome,
-            Some(current_style),
-            !enabled,
-        )
-    };
+    let (uri, enabled) = (current_style.uri.clone(), current_style.enabled);
+    let result = add::add_style(&uri, user_chrome, Some(current_style), &[], !enabled, force);
 
     // Recover both config and target file if add failed
-    if result.is_err() {
+    if let Err(e) = result {
+        error!("Error: {}", e);
         recover_failure(&config_backup, &file_backup, &target_path)?;
     }
 
@@ -121,7 +106,7 @@ fn update_style(style: &str, edit: bool) -> Result<()> {
     Ok(())
 }
 
-fn recover_failure(config_backup: &Config, file_backup: &str, target_path: &PathBuf) -> Result<()> {
+fn recover_failure(config_backup: &Config, file_backup: &str, target_path: &Path) -> Result<()> {
     eprintln!("\x1b[0;31;40mUnable to update style\x1b[0m");
     println!("Attempting to restore config and target file");
 
@@ -131,8 +116,7 @@ fn recover_failure(config_backup: &Config, file_backup: &str, target_path: &Path
     );
 
     // Recover target file
-    let file_result = fs::File::create(&target_path)
-        .and_then(|mut f| f.write_all(file_backup.as_bytes()))
+    let file_result = target::write(target_path, file_backup)
         .map_err(
             |_| "Unable to recover target file\nPlease ensure the file is not corrupted",
         );
@@ -0,0 +1,214 @@
+use std::path::{Path, PathBuf};
+use git::{self, Source};
+use config::StyleType;
+use errors::*;
+use std::env;
+
+// Get the type of a style from its name on the command line
+pub fn parse_type(name: &str) -> Option<StyleType> {
+    match name {
+        "userstyle" => Some(StyleType::Userstyle),
+        "local" => Some(StyleType::Local),
+        "remote" => Some(StyleType::Remote),
+        "git" => Some(StyleType::Git),
+        _ => None,
+    }
+}
+
+// Find out where a style comes from and get the canonical form of its URI
+// Local styles are stored with their absolute path, so this is stable across calls
+pub fn classify(uri: &str, style_type: Option<StyleType>) -> Result<(StyleType, String)> {
+    let is_url = uri.starts_with("http://") || uri.starts_with("https://");
+
+    // Files can always be identified by their scheme
+    if uri.starts_with("file://") {
+        return Ok((StyleType::Local, resolve_path(&uri.replacen("file://", "", 1))?));
+    }
+
+    // Git repositories on disk are stored with their absolute path
+    if uri.starts_with(git::PREFIX) || style_type == Some(StyleType::Git) {
+        let uri = if uri.starts_with(git::PREFIX) {
+            uri.to_owned()
+        } else {
+            [git::PREFIX, uri].concat()
+        };
+
+        let mut source = Source::parse(&uri)?;
+        if is_local_repo(&source.repo) {
+            source.repo = resolve_path(&source.repo)?;
+        }
+        return Ok((StyleType::Git, source.uri()));
+    }
+
+    match style_type {
+        Some(StyleType::Git) => unreachable!(),
+        Some(StyleType::Local) => Ok((StyleType::Local, resolve_path(uri)?)),
+        Some(StyleType::Remote) if is_url => Ok((StyleType::Remote, uri.to_owned())),
+        Some(StyleType::Remote) => Ok((StyleType::Remote, ["https://", uri].concat())),
+        Some(StyleType::Userstyle) => {
+            if !is_userstyle_id(uri) {
+                Err(format!("'{}' is not a userstyles.org id", uri))?;
+            }
+            Ok((StyleType::Userstyle, uri.to_owned()))
+        }
+        None if is_url => Ok((StyleType::Remote, uri.to_owned())),
+        None if is_userstyle_id(uri) => Ok((StyleType::Userstyle, uri.to_owned())),
+        None => {
+            if uri.starts_with('~') || uri.contains('/') || expand_home(uri)?.exists() {
+                Ok((StyleType::Local, resolve_path(uri)?))
+            } else {
+                Err(format!("Unable to find '{}', use '--type' to specify the type", uri))?
+            }
+        }
+    }
+}
+
+// Check if a repository is a path instead of a URL like `https://…` or `git@host:…`
+fn is_local_repo(repo: &str) -> bool {
+    if repo.contains("://") {
+        return false;
+    }
+
+    match (repo.find(':'), repo.find('/')) {
+        (Some(colon), Some(slash)) => slash < colon,
+        (Some(_), None) => false,
+        _ => true,
+    }
+}
+
+// Check if a URI is the numeric id of a style on userstyles.org
+fn is_userstyle_id(uri: &str) -> bool {
+    !uri.is_empty() && uri.chars().all(|c| c.is_ascii_digit())
+}
+
+// Get the absolute path of a local style
+fn resolve_path(path: &str) -> Result<String> {
+    let path = expand_home(path)?;
+    let path = path.canonicalize()
+        .map_err(|e| format!("Unable to find '{}': {}", path.to_string_lossy(), e))?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+// Replace a leading `~` with the home directory
+// Relative paths are resolved by the OS against the working directory
+pub fn expand_home(path: &str) -> Result<PathBuf> {
+    if path == "~" || path.starts_with("~/") {
+        let home = env::home_dir().ok_or("Unable to find home directory.")?;
+        Ok(home.join(path.replacen("~", "", 1).replacen("/", "", 1)))
+    } else {
+        Ok(Path::new(path).to_owned())
+    }
+}
+
+
+////////// TESTS //////////
+
+
+#[test]
+#[allow(non_snake_case)]
+fn classify__with_numeric_id__returns_userstyle() {
+    let result = classify("37035", None).unwrap();
+
+    assert_eq!(result, (StyleType::Userstyle, String::from("37035")));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn classify__with_https_url__returns_remote() {
+    let result = classify("https://example.com/style.css", None).unwrap();
+
+    assert_eq!(result, (StyleType::Remote, String::from("https://example.com/style.css")));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn classify__with_remote_type_and_no_scheme__adds_https() {
+    let result = classify("example.com/style.css", Some(StyleType::Remote)).unwrap();
+
+    assert_eq!(result, (StyleType::Remote, String::from("https://example.com/style.css")));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn classify__with_file_url__returns_absolute_path() {
+    let result = classify("file:///tmp/../tmp", None).unwrap();
+
+    assert_eq!(result.0, StyleType::Local);
+    assert!(result.1.ends_with("/tmp"));
+    assert!(!result.1.contains(".."));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn classify__with_relative_path__returns_absolute_path() {
+    let result = classify("./src/../Cargo.toml", None).unwrap();
+
+    let expected = env::current_dir().unwrap().join("Cargo.toml");
+    assert_eq!(result, (StyleType::Local, expected.to_string_lossy().into_owned()));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn classify__with_existing_file_name__returns_local() {
+    let result = classify("Cargo.toml", None).unwrap();
+
+    assert_eq!(result.0, StyleType::Local);
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn classify__with_userstyle_type_and_name__returns_error() {
+    let result = classify("Cargo.toml", Some(StyleType::Userstyle));
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn classify__with_missing_file__returns_error() {
+    let result = classify("./missing/style.css", None);
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn classify__with_git_type_and_relative_repo__returns_absolute_repo() {
+    let result = classify(".#style.css@main", Some(StyleType::Git)).unwrap();
+
+    let expected = format!("git+{}#style.css@main", env::current_dir().unwrap().to_string_lossy());
+    assert_eq!(result, (StyleType::Git, expected));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn classify__with_git_url__keeps_url() {
+    let uri = "git+https://example.com/themes.git#style.css";
+
+    let result = classify(uri, None).unwrap();
+
+    assert_eq!(result, (StyleType::Git, String::from(uri)));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn is_local_repo__with_scp_like_url__returns_false() {
+    assert!(!is_local_repo("git@example.com:themes.git"));
+    assert!(is_local_repo("./themes:old"));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn expand_home__with_tilde__returns_path_in_home() {
+    let result = expand_home("~/styles/style.css").unwrap();
+
+    assert_eq!(result, env::home_dir().unwrap().join("styles/style.css"));
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn expand_home__with_tilde_user__returns_unchanged_path() {
+    let result = expand_home("~user/style.css").unwrap();
+
+    assert_eq!(result, Path::new("~user/style.css"));
+}
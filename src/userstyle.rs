@@ -46,6 +46,15 @@ pub fn style<T: BufRead>(
         path,
         enabled,
         domain: None,
+        matchers: Vec::new(),
+        content_domain: None,
+        content_matchers: Vec::new(),
+        tags: Vec::new(),
+        order: 0,
+        updated: None,
+        css_override: None,
+        http: None,
+        commit: None,
         name: style.name,
         uri: userstyle_id.to_owned(),
         style_type: config::StyleType::Userstyle,
@@ -54,6 +63,19 @@ pub fn style<T: BufRead>(
     })
 }
 
+// Get the human-readable labels of all settings
+pub fn setting_labels(userstyle_id: &str) -> Result<HashMap<String, String>> {
+    let style = userstyles::get_style(userstyle_id.parse()?)?;
+
+    Ok(
+        style
+            .style_settings
+            .into_iter()
+            .map(|s| (s.install_key, s.label))
+            .collect(),
+    )
+}
+
 // Get the human-readable option labels
 fn style_options(setting: &StyleSetting) -> Vec<String> {
     let mut options = Vec::new();
@@ -0,0 +1,93 @@
+use notify::{self, DebouncedEvent, RecursiveMode, Watcher};
+use config::{Config, StyleType};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::time::Duration;
+use std::io::Read;
+use std::fs::File;
+use errors::*;
+use add;
+
+// Time to wait for more changes before reinstalling a style
+const DEBOUNCE_MS: u64 = 250;
+
+pub fn run() -> Result<()> {
+    let config = Config::load()?;
+
+    // Get the source files of all local styles
+    let sources: Vec<PathBuf> = config
+        .styles
+        .iter()
+        .filter(|s| s.style_type == StyleType::Local)
+        .map(|s| PathBuf::from(&s.uri))
+        .filter(|p| p.is_file())
+        .collect();
+
+    if sources.is_empty() {
+        Err("There are no local styles to watch")?;
+    }
+
+    // Watch the directories, editors often replace files instead of writing to them
+    let (tx, rx) = channel();
+    let mut watcher = notify::watcher(tx, Duration::from_millis(DEBOUNCE_MS))
+        .map_err(|e| format!("Unable to start watching: {}", e))?;
+    let dirs: HashSet<&Path> = sources.iter().filter_map(|p| p.parent()).collect();
+    for dir in dirs {
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| format!("Unable to watch '{}': {}", dir.to_string_lossy(), e))?;
+    }
+
+    println!("Watching {} styles, press Ctrl+C to stop", sources.len());
+
+    loop {
+        let path = match rx.recv().map_err(|_| "Watcher stopped unexpectedly")? {
+            DebouncedEvent::Create(path) |
+            DebouncedEvent::Write(path) |
+            DebouncedEvent::Rename(_, path) => path,
+            DebouncedEvent::Error(e, _) => {
+                error!("Watch error: {}", e);
+                continue;
+            }
+            _ => continue,
+        };
+
+        if !sources.contains(&path) {
+            continue;
+        }
+
+        // Keep watching when a single style can't be installed
+        println!();
+        if let Err(e) = reinstall(&path) {
+            error!("Error: {}", e);
+        }
+    }
+}
+
+// Install the new CSS of all styles using a source file
+fn reinstall(path: &Path) -> Result<()> {
+    let mut css = String::new();
+    File::open(path)?.read_to_string(&mut css)?;
+
+    // Load the config again in case it has been changed
+    let config = Config::load()?;
+    let styles = config
+        .styles
+        .iter()
+        .filter(|s| s.style_type == StyleType::Local && Path::new(&s.uri) == path);
+
+    for style in styles {
+        if !style.enabled {
+            println!("Skipping disabled style '{}'", style.name);
+            continue;
+        }
+
+        // Invalid CSS leaves the last working version installed
+        add::reinstall(style, &css)?;
+
+        println!("Reinstalled style '{}'", style.name);
+    }
+
+    Ok(())
+}